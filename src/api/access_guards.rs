@@ -0,0 +1,94 @@
+use rocket::Request;
+use rocket::http::Status;
+use rocket::request::{ FromRequest, Outcome };
+use serde_json::json;
+use crate::api::models::{ ApiError, ApiErrorCode, Caller, Role };
+use crate::api::ApiKeyService;
+
+const BEARER_PREFIX: &str = "Bearer ";
+
+// Rocket only hands catchers the status, so the error a guard failed with is cached on the request for them
+pub struct AccessFailure(pub Option<ApiError>);
+
+impl AccessFailure {
+    pub fn take_error(request: &Request<'_>, fallback_code: ApiErrorCode) -> ApiError {
+        request.local_cache(|| AccessFailure(None)).0.clone().unwrap_or_else(|| ApiError::new(fallback_code))
+    }
+}
+
+pub struct GuestAccess(pub Caller);
+pub struct BartenderAccess;
+pub struct AdminAccess(pub Caller);
+// Key management is also open to requests from the machine itself until the first admin key has been created.
+// After that a reverse proxy on the same machine would make every request look local
+pub struct LocalAdminAccess;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for GuestAccess {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        authorize(request, Role::Guest).map(GuestAccess)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BartenderAccess {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        authorize(request, Role::Bartender).map(|_| BartenderAccess)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminAccess {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        authorize(request, Role::Admin).map(AdminAccess)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LocalAdminAccess {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let has_admin_key = request.rocket().state::<ApiKeyService>().is_some_and(|api_key_service| api_key_service.has_admin_key());
+        if !has_admin_key && request.remote().is_some_and(|remote| remote.ip().is_loopback()) {
+            return Outcome::Success(LocalAdminAccess);
+        }
+        authorize(request, Role::Admin).map(|_| LocalAdminAccess)
+    }
+}
+
+fn authorize(request: &Request<'_>, required_role: Role) -> Outcome<Caller, ApiError> {
+    // Without a key service the config didn't load and only diagnostics are served
+    let api_key_service = match request.rocket().state::<ApiKeyService>() {
+        Some(api_key_service) => api_key_service,
+        None => return Outcome::Success(Caller { role: Role::Admin, api_key_id: None, name: None })
+    };
+    let caller = match api_key_service.authenticate(get_api_key(request)) {
+        Ok(caller) => caller,
+        Err(error) => return fail(request, Status::Unauthorized, error)
+    };
+    if caller.role < required_role {
+        let error = ApiError::with_details(ApiErrorCode::InsufficientRole, json!({ "role": caller.role, "required_role": required_role }));
+        return fail(request, Status::Forbidden, error);
+    }
+    Outcome::Success(caller)
+}
+
+pub fn get_api_key<'a>(request: &'a Request<'_>) -> Option<&'a str> {
+    let authorization_key = request.headers().get_one("Authorization")
+        .and_then(|authorization| authorization.strip_prefix(BEARER_PREFIX));
+    authorization_key.or_else(|| request.headers().get_one("X-Api-Key"))
+        .map(|key| key.trim())
+        .filter(|key| !key.is_empty())
+}
+
+fn fail<T>(request: &Request<'_>, status: Status, error: ApiError) -> Outcome<T, ApiError> {
+    request.local_cache(|| AccessFailure(Some(error.clone())));
+    Outcome::Failure((status, error))
+}
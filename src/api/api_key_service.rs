@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use serde_json::json;
+use sha2::{ Digest, Sha256 };
+use uuid::Uuid;
+use crate::api::models::{ ApiError, ApiErrorCode, ApiKey, Caller, CreatedApiKey, ListedApiKey, NewApiKey, Role };
+
+pub struct ApiKeyService {
+    api_keys: RwLock<Vec<ApiKey>>,
+    api_keys_file_path: PathBuf,
+    is_enabled: bool
+}
+
+impl ApiKeyService {
+    pub fn new(api_keys: Vec<ApiKey>, api_keys_file_path: PathBuf, is_enabled: bool) -> ApiKeyService {
+        ApiKeyService { api_keys: RwLock::new(api_keys), api_keys_file_path, is_enabled }
+    }
+
+    // Without authentication every request is treated as coming from an admin
+    pub fn authenticate(&self, key: Option<&str>) -> Result<Caller, ApiError> {
+        if !self.is_enabled {
+            return Ok(Caller { role: Role::Admin, api_key_id: None, name: None });
+        }
+        let key = key.ok_or_else(|| ApiError::new(ApiErrorCode::AuthenticationRequired))?;
+        let key_hash = Self::hash_key(key);
+        self.api_keys.read().unwrap().iter()
+            .find(|api_key| api_key.key_hash == key_hash)
+            .map(|api_key| Caller { role: api_key.role, api_key_id: Some(api_key.id), name: Some(api_key.name.clone()) })
+            .ok_or_else(|| ApiError::new(ApiErrorCode::AuthenticationRequired))
+    }
+
+    pub fn has_admin_key(&self) -> bool {
+        self.api_keys.read().unwrap().iter().any(|api_key| api_key.role == Role::Admin)
+    }
+
+    pub fn get_api_keys(&self) -> Vec<ListedApiKey> {
+        self.api_keys.read().unwrap().iter()
+            .map(|api_key| ListedApiKey { id: api_key.id, name: api_key.name.clone(), role: api_key.role })
+            .collect()
+    }
+
+    pub fn create_api_key(&self, new_api_key: NewApiKey) -> Result<CreatedApiKey, ApiError> {
+        let name = new_api_key.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ApiError::new(ApiErrorCode::ApiKeyNameMissing));
+        }
+        let key = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let api_key = ApiKey { id: Uuid::new_v4(), name, role: new_api_key.role, key_hash: Self::hash_key(&key) };
+        let mut api_keys = self.api_keys.write().unwrap();
+        let mut updated_api_keys = api_keys.clone();
+        updated_api_keys.push(api_key.clone());
+        self.save(&updated_api_keys)?;
+        *api_keys = updated_api_keys;
+        Ok(CreatedApiKey { id: api_key.id, name: api_key.name, role: api_key.role, key })
+    }
+
+    pub fn delete_api_key(&self, id: Uuid) -> Result<(), ApiError> {
+        let mut api_keys = self.api_keys.write().unwrap();
+        if !api_keys.iter().any(|api_key| api_key.id == id) {
+            return Err(ApiError::with_details(ApiErrorCode::ApiKeyNotFound, json!({ "id": id })));
+        }
+        let updated_api_keys: Vec<ApiKey> = api_keys.iter().filter(|api_key| api_key.id != id).cloned().collect();
+        self.save(&updated_api_keys)?;
+        *api_keys = updated_api_keys;
+        Ok(())
+    }
+
+    fn save(&self, api_keys: &[ApiKey]) -> Result<(), ApiError> {
+        let api_keys_json = serde_json::to_string_pretty(api_keys).map_err(Self::create_save_error)?;
+        if let Some(api_keys_directory) = self.api_keys_file_path.parent() {
+            fs::create_dir_all(api_keys_directory).map_err(Self::create_save_error)?;
+        }
+        fs::write(&self.api_keys_file_path, api_keys_json).map_err(Self::create_save_error)
+    }
+
+    fn create_save_error<E: ToString>(error: E) -> ApiError {
+        ApiError::with_details(ApiErrorCode::ApiKeysNotSaved, json!({ "error": error.to_string() }))
+    }
+
+    pub fn hash_key(key: &str) -> String {
+        Sha256::digest(key.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
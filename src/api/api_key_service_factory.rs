@@ -0,0 +1,20 @@
+use std::fs;
+use std::io::ErrorKind;
+use crate::api::models::{ ApiKey, Config };
+use crate::api::ApiKeyService;
+
+pub struct ApiKeyServiceFactory {}
+
+impl ApiKeyServiceFactory {
+    pub fn create(config: &Config) -> Result<ApiKeyService, String> {
+        let file_path = config.api_keys_file_path.clone();
+        let api_keys: Vec<ApiKey> = match fs::read_to_string(&file_path) {
+            Ok(api_keys_json) => serde_json::from_str(&api_keys_json)
+                .map_err(|error| format!("Couldn't parse {}: {}", file_path.display(), error))?,
+            // No keys have been created yet
+            Err(error) if error.kind() == ErrorKind::NotFound => vec![],
+            Err(error) => return Err(format!("Couldn't read {}: {}", file_path.display(), error))
+        };
+        Ok(ApiKeyService::new(api_keys, file_path, config.is_authentication_enabled))
+    }
+}
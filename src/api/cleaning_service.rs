@@ -0,0 +1,236 @@
+use std::collections::BTreeSet;
+use std::sync::{ Arc, Condvar, Mutex };
+use std::thread;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+use serde_json::json;
+use uuid::Uuid;
+use crate::api::models::{ ApiError, ApiErrorCode, CleaningProgram, CleaningRun, CleaningRunStatus, CleaningStep, NewCleaningRun };
+use crate::api::PumpService;
+
+// How often a pumping step checks whether its jobs are done
+const PUMPING_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// The run along with what the API has asked of it
+struct CleaningRunControl {
+    cleaning_run: Option<CleaningRun>,
+    is_confirmed: bool,
+    is_cancelled: bool
+}
+
+pub struct CleaningService {
+    cleaning_programs: Vec<CleaningProgram>,
+    pump_service: Arc<Mutex<PumpService>>,
+    cleaning_run_pair: (Mutex<CleaningRunControl>, Condvar),
+    run_thread: Mutex<Option<thread::JoinHandle<()>>>
+}
+
+impl CleaningService {
+    pub fn new(cleaning_programs: Vec<CleaningProgram>, pump_service: Arc<Mutex<PumpService>>) -> CleaningService {
+        CleaningService {
+            cleaning_programs,
+            pump_service,
+            cleaning_run_pair: (Mutex::new(CleaningRunControl { cleaning_run: None, is_confirmed: false, is_cancelled: false }), Condvar::new()),
+            run_thread: Mutex::new(None)
+        }
+    }
+
+    pub fn get_cleaning_programs(&self) -> Vec<CleaningProgram> {
+        self.cleaning_programs.clone()
+    }
+
+    pub fn get_cleaning_run(&self) -> Result<CleaningRun, ApiError> {
+        self.cleaning_run_pair.0.lock().unwrap().cleaning_run.clone()
+            .ok_or_else(|| ApiError::new(ApiErrorCode::CleaningRunNotFound))
+    }
+
+    // Only one program runs at a time and only once the pump queue is empty, then drinks wait until it's done
+    pub fn start_cleaning_run(cleaning_service: &Arc<CleaningService>, new_cleaning_run: NewCleaningRun) -> Result<CleaningRun, ApiError> {
+        let cleaning_program = cleaning_service.cleaning_programs.iter().find(|cleaning_program| cleaning_program.id == new_cleaning_run.program_id)
+            .ok_or_else(|| ApiError::with_details(ApiErrorCode::CleaningProgramNotFound, json!({ "program_id": new_cleaning_run.program_id })))?
+            .clone();
+        let (cleaning_run_mutex, _) = &cleaning_service.cleaning_run_pair;
+        let mut cleaning_run_control = cleaning_run_mutex.lock().unwrap();
+        if let Some(cleaning_run) = cleaning_run_control.cleaning_run.as_ref().filter(|cleaning_run| !cleaning_run.status.is_finished()) {
+            return Err(ApiError::with_details(ApiErrorCode::CleaningRunActive, json!({ "program_id": cleaning_run.program_id })));
+        }
+        cleaning_service.pump_service.lock().unwrap().start_cleaning()?;
+        let cleaning_run = CleaningRun {
+            id: Uuid::new_v4(),
+            program_id: cleaning_program.id.clone(),
+            program_name: cleaning_program.name.clone(),
+            status: Self::get_step_status(&cleaning_program.steps[0]),
+            step_index: 0,
+            step_count: cleaning_program.steps.len(),
+            step: Some(cleaning_program.steps[0].clone()),
+            step_ends_at: None,
+            started_at: Self::get_now_time(),
+            finished_at: None,
+            error_code: None,
+            error_details: None
+        };
+        *cleaning_run_control = CleaningRunControl { cleaning_run: Some(cleaning_run.clone()), is_confirmed: false, is_cancelled: false };
+        drop(cleaning_run_control);
+        let run_cleaning_service = cleaning_service.clone();
+        let run_thread = thread::spawn(move || run_cleaning_service.run_cleaning_program(&cleaning_program));
+        // The previous run has finished, so its thread is done or about to be
+        if let Some(previous_run_thread) = cleaning_service.run_thread.lock().unwrap().replace(run_thread) {
+            let _ = previous_run_thread.join();
+        }
+        Ok(cleaning_run)
+    }
+
+    pub fn confirm_prompt(&self) -> Result<CleaningRun, ApiError> {
+        let (cleaning_run_mutex, cvar) = &self.cleaning_run_pair;
+        let mut cleaning_run_control = cleaning_run_mutex.lock().unwrap();
+        let cleaning_run = cleaning_run_control.cleaning_run.clone().ok_or_else(|| ApiError::new(ApiErrorCode::CleaningRunNotFound))?;
+        if cleaning_run.status != CleaningRunStatus::AwaitingConfirmation {
+            return Err(ApiError::with_details(ApiErrorCode::CleaningRunNotAwaitingConfirmation, json!({ "status": cleaning_run.status })));
+        }
+        cleaning_run_control.is_confirmed = true;
+        cvar.notify_all();
+        Ok(cleaning_run)
+    }
+
+    // Stops the pump straight away if a step is pumping
+    pub fn cancel_cleaning_run(&self) -> Result<CleaningRun, ApiError> {
+        let (cleaning_run_mutex, cvar) = &self.cleaning_run_pair;
+        let mut cleaning_run_control = cleaning_run_mutex.lock().unwrap();
+        let cleaning_run = cleaning_run_control.cleaning_run.clone()
+            .filter(|cleaning_run| !cleaning_run.status.is_finished())
+            .ok_or_else(|| ApiError::new(ApiErrorCode::CleaningRunNotFound))?;
+        cleaning_run_control.is_cancelled = true;
+        cvar.notify_all();
+        Ok(cleaning_run)
+    }
+
+    pub fn kill_cleaning_run(&self) {
+        let _ = self.cancel_cleaning_run();
+        if let Some(run_thread) = self.run_thread.lock().unwrap().take() {
+            let _ = run_thread.join();
+        }
+    }
+
+    fn run_cleaning_program(&self, cleaning_program: &CleaningProgram) {
+        let mut cleaned_pump_numbers = BTreeSet::new();
+        let mut result = Ok(());
+        for (step_index, cleaning_step) in cleaning_program.steps.iter().enumerate() {
+            result = match cleaning_step {
+                CleaningStep::Pump { pump_numbers, ml, dead_volumes } => self.run_pump_step(step_index, cleaning_step, pump_numbers, *ml, *dead_volumes)
+                    .map(|pumped_pump_numbers| cleaned_pump_numbers.extend(pumped_pump_numbers)),
+                CleaningStep::Wait { seconds } => {
+                    let duration = Duration::from_secs(*seconds);
+                    self.start_step(step_index, cleaning_step, Some(Self::get_now_time() + duration.as_millis() as u64));
+                    self.wait_while(Some(duration), |_| true);
+                    Ok(())
+                },
+                CleaningStep::Prompt { .. } => {
+                    self.start_step(step_index, cleaning_step, None);
+                    self.wait_while(None, |cleaning_run_control| !cleaning_run_control.is_confirmed);
+                    self.cleaning_run_pair.0.lock().unwrap().is_confirmed = false;
+                    Ok(())
+                }
+            };
+            if result.is_err() || self.is_cancelled() {
+                break;
+            }
+        }
+        let status = match &result {
+            Err(_) => CleaningRunStatus::Failed,
+            Ok(_) if self.is_cancelled() => CleaningRunStatus::Cancelled,
+            Ok(_) => CleaningRunStatus::Completed
+        };
+        let cleaned_pump_numbers: Vec<u8> = match status {
+            CleaningRunStatus::Completed => cleaned_pump_numbers.into_iter().collect(),
+            _ => vec![]
+        };
+        self.pump_service.lock().unwrap().finish_cleaning(&cleaned_pump_numbers);
+        let mut cleaning_run_control = self.cleaning_run_pair.0.lock().unwrap();
+        if let Some(cleaning_run) = cleaning_run_control.cleaning_run.as_mut() {
+            cleaning_run.status = status;
+            cleaning_run.step_ends_at = None;
+            cleaning_run.finished_at = Some(Self::get_now_time());
+            if let Err(error) = result {
+                cleaning_run.error_code = Some(error.code);
+                cleaning_run.error_details = error.details;
+            }
+            log::info!("Cleaning program {} finished as {:?}", cleaning_run.program_id, status);
+        }
+    }
+
+    // Queues the step's jobs as one group and waits for the daemon to pump them, returning the pumps whose jobs ran in full
+    fn run_pump_step(&self, step_index: usize, cleaning_step: &CleaningStep, pump_numbers: &[u8], ml: Option<f32>, dead_volumes: Option<f32>) -> Result<Vec<u8>, ApiError> {
+        let queued_pump_group = {
+            let pump_service = self.pump_service.lock().unwrap();
+            let pump_numbers: Vec<u8> = match pump_numbers.is_empty() {
+                true => (1..=pump_service.get_number_of_pumps()).collect(),
+                false => pump_numbers.to_vec()
+            };
+            let mut pump_amounts = vec![];
+            for pump_number in pump_numbers {
+                let ml_to_pump = match (ml, dead_volumes) {
+                    (Some(ml), _) => ml,
+                    (None, Some(dead_volumes)) => pump_service.get_dead_volume_ml(pump_number).map_or(0.0, |dead_volume_ml| dead_volume_ml * dead_volumes),
+                    (None, None) => 0.0
+                };
+                pump_amounts.push((pump_number, ml_to_pump));
+            }
+            pump_service.enqueue_cleaning_jobs(&pump_amounts)?
+        };
+        self.start_step(step_index, cleaning_step, Some(queued_pump_group.estimate.estimated_completion_time));
+        loop {
+            if !self.pump_service.lock().unwrap().is_group_queued(queued_pump_group.group_id) {
+                break;
+            }
+            if self.wait_while(Some(PUMPING_CHECK_INTERVAL), |_| true) {
+                self.pump_service.lock().unwrap().cancel_group(queued_pump_group.group_id);
+                break;
+            }
+        }
+        // The group also leaves the queue when it's stopped or cancelled through the pump API, which cancels the run
+        match self.pump_service.lock().unwrap().get_group_record(queued_pump_group.group_id) {
+            Some(pump_group_record) if !pump_group_record.is_dropped => Ok(pump_group_record.completed_pump_numbers),
+            _ => {
+                self.cleaning_run_pair.0.lock().unwrap().is_cancelled = true;
+                Ok(vec![])
+            }
+        }
+    }
+
+    fn get_step_status(cleaning_step: &CleaningStep) -> CleaningRunStatus {
+        match cleaning_step {
+            CleaningStep::Pump { .. } => CleaningRunStatus::Pumping,
+            CleaningStep::Wait { .. } => CleaningRunStatus::Waiting,
+            CleaningStep::Prompt { .. } => CleaningRunStatus::AwaitingConfirmation
+        }
+    }
+
+    fn start_step(&self, step_index: usize, cleaning_step: &CleaningStep, step_ends_at: Option<u64>) {
+        let mut cleaning_run_control = self.cleaning_run_pair.0.lock().unwrap();
+        if let Some(cleaning_run) = cleaning_run_control.cleaning_run.as_mut() {
+            cleaning_run.status = Self::get_step_status(cleaning_step);
+            cleaning_run.step_index = step_index;
+            cleaning_run.step = Some(cleaning_step.clone());
+            cleaning_run.step_ends_at = step_ends_at;
+        }
+    }
+
+    // Waits until the condition no longer holds, the timeout passes or the run is cancelled, telling whether it was cancelled
+    fn wait_while(&self, timeout: Option<Duration>, condition: fn(&CleaningRunControl) -> bool) -> bool {
+        let (cleaning_run_mutex, cvar) = &self.cleaning_run_pair;
+        let cleaning_run_control = cleaning_run_mutex.lock().unwrap();
+        let should_wait = |cleaning_run_control: &mut CleaningRunControl| !cleaning_run_control.is_cancelled && condition(cleaning_run_control);
+        let cleaning_run_control = match timeout {
+            Some(timeout) => cvar.wait_timeout_while(cleaning_run_control, timeout, should_wait).unwrap().0,
+            None => cvar.wait_while(cleaning_run_control, should_wait).unwrap()
+        };
+        cleaning_run_control.is_cancelled
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cleaning_run_pair.0.lock().unwrap().is_cancelled
+    }
+
+    fn get_now_time() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or(0)
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::{ Arc, Mutex };
+use crate::api::models::{ CleaningProgram, CleaningStep, Config };
+use crate::api::{ CleaningService, PumpService };
+
+const BUILT_IN_CLEANING_PROGRAMS_JSON: &str = include_str!("../../resources/cleaning_programs.json");
+
+pub struct CleaningServiceFactory {}
+
+impl CleaningServiceFactory {
+    // Programs in the file replace the built in ones with the same id and are added after the rest
+    pub fn create(config: &Config, pump_service: Arc<Mutex<PumpService>>) -> Result<CleaningService, String> {
+        let mut cleaning_programs: Vec<CleaningProgram> = serde_json::from_str(BUILT_IN_CLEANING_PROGRAMS_JSON)
+            .map_err(|error| format!("Couldn't parse the built in cleaning programs: {}", error))?;
+        let file_path = &config.cleaning_programs_file_path;
+        let custom_cleaning_programs: Vec<CleaningProgram> = match fs::read_to_string(file_path) {
+            Ok(cleaning_programs_json) => serde_json::from_str(&cleaning_programs_json)
+                .map_err(|error| format!("Couldn't parse {}: {}", file_path.display(), error))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => vec![],
+            Err(error) => return Err(format!("Couldn't read {}: {}", file_path.display(), error))
+        };
+        for custom_cleaning_program in custom_cleaning_programs {
+            match cleaning_programs.iter_mut().find(|cleaning_program| cleaning_program.id == custom_cleaning_program.id) {
+                Some(cleaning_program) => *cleaning_program = custom_cleaning_program,
+                None => cleaning_programs.push(custom_cleaning_program)
+            }
+        }
+        Self::validate_cleaning_programs(&cleaning_programs, config.get_number_of_pumps())
+            .map_err(|error| format!("Invalid cleaning programs in {}: {}", file_path.display(), error))?;
+        Ok(CleaningService::new(cleaning_programs, pump_service))
+    }
+
+    fn validate_cleaning_programs(cleaning_programs: &[CleaningProgram], number_of_pumps: u8) -> Result<(), String> {
+        let mut ids = HashSet::new();
+        for cleaning_program in cleaning_programs {
+            if cleaning_program.id.trim().is_empty() {
+                return Err("every program needs an id".to_string());
+            }
+            if !ids.insert(&cleaning_program.id) {
+                return Err(format!("the id {} is used more than once", cleaning_program.id));
+            }
+            if cleaning_program.steps.is_empty() {
+                return Err(format!("{} has no steps", cleaning_program.id));
+            }
+            for (step_index, cleaning_step) in cleaning_program.steps.iter().enumerate() {
+                if let CleaningStep::Pump { pump_numbers, ml, dead_volumes } = cleaning_step {
+                    let has_one_amount = match (ml, dead_volumes) {
+                        (Some(ml), None) => *ml > 0.0,
+                        (None, Some(dead_volumes)) => *dead_volumes > 0.0,
+                        _ => false
+                    };
+                    if !has_one_amount {
+                        return Err(format!("step {} of {} needs either ml or deadVolumes greater than 0", step_index + 1, cleaning_program.id));
+                    }
+                    if let Some(pump_number) = pump_numbers.iter().find(|pump_number| **pump_number == 0 || **pump_number > number_of_pumps) {
+                        return Err(format!("step {} of {} uses pump {} but there are {} pumps", step_index + 1, cleaning_program.id, pump_number, number_of_pumps));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use toml::Value;
+use toml::value::Table;
+#[cfg(feature = "bff")]
+use crate::api::models::{ ConsumptionLimit, CupOverflowPolicy };
+use crate::api::models::{ AcceptedLocales, Config, ConfigError, MaintenanceThreshold, PumpLine, UnitOfMeasure, UnitsOfMeasure };
+
+const CONFIG_FILE_PATH_KEY: &str = "config_file_path";
+const DEFAULT_CONFIG_FILE_PATH: &str = ".drink-o-matic/config.toml";
+const DEFAULT_RPI_CHIP_NAME: &str = "/dev/gpiochip0";
+// About a metre of 4 mm tubing
+const DEFAULT_DEAD_VOLUME_ML: f32 = 12.0;
+// Once max_on_seconds is set, how long a pump rests after running that long
+const DEFAULT_COOL_DOWN_SECONDS: f32 = 30.0;
+// Enough to pull a few drops back from the nozzle
+const DEFAULT_RETRACT_SECONDS: f32 = 0.25;
+const DEFAULT_PUMPS_FILE_PATH: &str = ".drink-o-matic/pumps.json";
+#[cfg(feature = "bff")]
+const DEFAULT_SETTINGS_FILE_PATH: &str = ".drink-o-matic/settings.json";
+#[cfg(feature = "bff")]
+const DEFAULT_GUESTS_FILE_PATH: &str = ".drink-o-matic/guests.json";
+// Grams of pure alcohol in one US standard drink
+#[cfg(feature = "bff")]
+const DEFAULT_STANDARD_DRINK_GRAMS: f32 = 14.0;
+// 1.5 US fl oz
+const DEFAULT_SHOT_ML: f32 = 44.0;
+// A dash is 1/32 and a splash 1/6 US fl oz, the shot is added from shot_ml
+const DEFAULT_UNITS_OF_MEASURE: &[(&str, f32)] = &[("ml", 1.0), ("cl", 10.0), ("fl_oz", 29.5735), ("dash", 0.924), ("splash", 4.929)];
+#[cfg(feature = "bff")]
+const DEFAULT_CUP_FILL_RATIO: f32 = 1.0;
+const DEFAULT_STRINGS_XML_FILE_PATH: &str = ".drink-o-matic/strings.xml";
+const DEFAULT_LOCALE: &str = "en";
+const DEFAULT_LOCK_FILE_PATH: &str = ".drink-o-matic/drink-o-matic.lock";
+const DEFAULT_API_KEYS_FILE_PATH: &str = ".drink-o-matic/api_keys.json";
+const DEFAULT_CORS_ALLOWED_ORIGINS: &[&str] = &["*"];
+const DEFAULT_CORS_ALLOWED_METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+const DEFAULT_CORS_ALLOWED_HEADERS: &[&str] = &["Content-Type", "Authorization", "X-Api-Key", "Accept-Language", "Idempotency-Key"];
+const DEFAULT_IDEMPOTENCY_WINDOW_SECONDS: u64 = 3600;
+const DEFAULT_SCHEDULES_FILE_PATH: &str = ".drink-o-matic/schedules.json";
+const DEFAULT_CLEANING_PROGRAMS_FILE_PATH: &str = ".drink-o-matic/cleaning_programs.json";
+const DEFAULT_CLEANING_DUE_AFTER_IDLE_HOURS: f32 = 24.0;
+const DEFAULT_CLEANING_DUE_AFTER_ML: f32 = 5000.0;
+// Silicone tubing in a pump head lasts for about this much, the motors of cheap diaphragm pumps for about this long
+const DEFAULT_REPLACE_TUBING_AFTER_ML: f32 = 100000.0;
+const DEFAULT_REPLACE_PUMP_AFTER_ON_HOURS: f32 = 200.0;
+
+pub struct ConfigFactory {}
+
+impl ConfigFactory {
+    // Loads the config file and applies environment overrides, collecting every problem found along the way
+    pub fn create() -> Result<Config, Vec<ConfigError>> {
+        let home_dir = match dirs::home_dir() {
+            Some(home_dir) => home_dir,
+            None => return Err(vec![ConfigError { key: "home_dir".to_string(), message: "couldn't determine the home directory".to_string() }])
+        };
+        let mut reader = ConfigReader::new();
+        reader.load_file(home_dir.join(dotenv::var(CONFIG_FILE_PATH_KEY.to_uppercase()).unwrap_or_else(|_| DEFAULT_CONFIG_FILE_PATH.to_string())));
+
+        let rpi_chip_name = reader.read_string("rpi_chip_name").unwrap_or_else(|| DEFAULT_RPI_CHIP_NAME.to_string());
+        if rpi_chip_name.trim().is_empty() {
+            reader.add_error("rpi_chip_name", "must not be empty");
+        }
+        let ordered_pump_pin_numbers = reader.read_u32_list("ordered_pump_pin_numbers");
+        if let Some(pin_numbers) = &ordered_pump_pin_numbers {
+            Self::validate_pin_numbers(&mut reader, "ordered_pump_pin_numbers", pin_numbers);
+        }
+        else {
+            reader.add_missing_error("ordered_pump_pin_numbers");
+        }
+        let milliseconds_per_ml = reader.read_u64("milliseconds_per_ml");
+        match milliseconds_per_ml {
+            Some(0) => reader.add_error("milliseconds_per_ml", "must be greater than 0"),
+            None => reader.add_missing_error("milliseconds_per_ml"),
+            _ => {}
+        }
+        let is_relay_inverted = reader.read_bool("is_relay_inverted");
+        if is_relay_inverted.is_none() {
+            reader.add_missing_error("is_relay_inverted");
+        }
+        let number_of_pumps = ordered_pump_pin_numbers.as_ref().map_or(0, |pin_numbers| pin_numbers.len());
+        // 0 for a pump without one; GPIO 0 is kept for HAT EEPROMs anyway
+        let direction_pin_numbers: Vec<Option<u32>> = match reader.read_u32_list("ordered_direction_pin_numbers") {
+            Some(pin_numbers) if pin_numbers.len() == number_of_pumps => pin_numbers.into_iter().map(|pin_number| Some(pin_number).filter(|pin_number| *pin_number > 0)).collect(),
+            Some(pin_numbers) => {
+                reader.add_error("ordered_direction_pin_numbers", &format!("expected a pin number or 0 for each of the {} pumps but got {}", number_of_pumps, pin_numbers.len()));
+                vec![None; number_of_pumps]
+            },
+            None => vec![None; number_of_pumps]
+        };
+        let mut seen_pin_numbers: HashSet<u32> = ordered_pump_pin_numbers.iter().flatten().copied().collect();
+        for direction_pin_number in direction_pin_numbers.iter().flatten() {
+            if !seen_pin_numbers.insert(*direction_pin_number) {
+                reader.add_error("ordered_direction_pin_numbers", &format!("pin {} is used more than once or also runs a pump", direction_pin_number));
+            }
+        }
+        let dead_volumes_ml = Self::read_per_pump_f32(&mut reader, "dead_volume_ml", "pump_dead_volumes_ml", number_of_pumps, DEFAULT_DEAD_VOLUME_ML);
+        if dead_volumes_ml.iter().any(|dead_volume_ml| *dead_volume_ml <= 0.0) {
+            reader.add_error("dead_volume_ml", "must be greater than 0, as must every entry of pump_dead_volumes_ml");
+        }
+        // No limit unless the host sets one
+        let max_on_seconds = Self::read_per_pump_f32(&mut reader, "max_on_seconds", "pump_max_on_seconds", number_of_pumps, 0.0);
+        if max_on_seconds.iter().any(|max_on_seconds| *max_on_seconds < 0.0) {
+            reader.add_error("max_on_seconds", "must be 0 or more, as must every entry of pump_max_on_seconds");
+        }
+        let cool_down_seconds = Self::read_per_pump_f32(&mut reader, "cool_down_seconds", "pump_cool_down_seconds", number_of_pumps, DEFAULT_COOL_DOWN_SECONDS);
+        if cool_down_seconds.iter().any(|cool_down_seconds| *cool_down_seconds < 0.0) {
+            reader.add_error("cool_down_seconds", "must be 0 or more, as must every entry of pump_cool_down_seconds");
+        }
+        // A pump that has to stop after max_on_seconds has to rest for some time too
+        else if max_on_seconds.iter().zip(&cool_down_seconds).any(|(max_on_seconds, cool_down_seconds)| *max_on_seconds > 0.0 && *cool_down_seconds <= 0.0) {
+            reader.add_error("cool_down_seconds", "must be greater than 0 for every pump with a max_on_seconds, as must its entry of pump_cool_down_seconds");
+        }
+        // Only pumps with a direction line can run backwards
+        let retract_seconds = Self::read_per_pump_f32(&mut reader, "retract_seconds", "pump_retract_seconds", number_of_pumps, DEFAULT_RETRACT_SECONDS);
+        if retract_seconds.iter().any(|retract_seconds| *retract_seconds < 0.0) {
+            reader.add_error("retract_seconds", "must be 0 or more, as must every entry of pump_retract_seconds");
+        }
+        let pump_lines = (0..number_of_pumps)
+            .map(|index| PumpLine {
+                dead_volume_ml: dead_volumes_ml[index],
+                max_on_milliseconds: Some((max_on_seconds[index] * 1000.0) as u64).filter(|max_on_milliseconds| *max_on_milliseconds > 0),
+                cool_down_milliseconds: (cool_down_seconds[index].max(0.0) * 1000.0) as u64,
+                retract_milliseconds: direction_pin_numbers[index].map_or(0, |_| (retract_seconds[index].max(0.0) * 1000.0) as u64)
+            })
+            .collect();
+        let pumps_file_path = reader.read_string("pumps_file_path").unwrap_or_else(|| DEFAULT_PUMPS_FILE_PATH.to_string());
+        #[cfg(feature = "bff")]
+        let settings_file_path = reader.read_string("settings_file_path").unwrap_or_else(|| DEFAULT_SETTINGS_FILE_PATH.to_string());
+        #[cfg(feature = "bff")]
+        let guests_file_path = reader.read_string("guests_file_path").unwrap_or_else(|| DEFAULT_GUESTS_FILE_PATH.to_string());
+        // No limits unless the host sets some
+        #[cfg(feature = "bff")]
+        let consumption_limits = reader.read_consumption_limits("consumption_limits").unwrap_or_default();
+        #[cfg(feature = "bff")]
+        let standard_drink_grams = reader.read_f32("standard_drink_grams").unwrap_or(DEFAULT_STANDARD_DRINK_GRAMS);
+        #[cfg(feature = "bff")]
+        if standard_drink_grams <= 0.0 {
+            reader.add_error("standard_drink_grams", "must be greater than 0");
+        }
+        // Also defines the shot unit, so it's read without the "bff" feature too
+        let shot_ml = reader.read_f32("shot_ml").unwrap_or(DEFAULT_SHOT_ML);
+        if shot_ml <= 0.0 {
+            reader.add_error("shot_ml", "must be greater than 0");
+        }
+        let units_of_measure = Self::read_units_of_measure(&mut reader, "units_of_measure", shot_ml);
+        #[cfg(feature = "bff")]
+        let default_cup_fill_ratio = reader.read_f32("default_cup_fill_ratio").unwrap_or(DEFAULT_CUP_FILL_RATIO);
+        #[cfg(feature = "bff")]
+        if default_cup_fill_ratio <= 0.0 || default_cup_fill_ratio > 1.0 {
+            reader.add_error("default_cup_fill_ratio", "must be greater than 0 and at most 1");
+        }
+        #[cfg(feature = "bff")]
+        let cup_overflow_policy = match reader.read_string("cup_overflow_policy").map(|policy| policy.to_lowercase()).as_deref() {
+            None | Some("reject") => CupOverflowPolicy::Reject,
+            Some("scale") => CupOverflowPolicy::Scale,
+            Some(policy) => {
+                reader.add_error("cup_overflow_policy", &format!("expected reject or scale but got \"{}\"", policy));
+                CupOverflowPolicy::Reject
+            }
+        };
+        let strings_xml_file_path = reader.read_string("strings_xml_file_path").unwrap_or_else(|| DEFAULT_STRINGS_XML_FILE_PATH.to_string());
+        let default_locale = AcceptedLocales::normalize(&reader.read_string("default_locale").unwrap_or_else(|| DEFAULT_LOCALE.to_string()));
+        if default_locale.is_empty() {
+            reader.add_error("default_locale", "must not be empty");
+        }
+        let log_locale = reader.read_string("log_locale").map_or(default_locale.clone(), |log_locale| AcceptedLocales::normalize(&log_locale));
+        let lock_file_path = reader.read_string("lock_file_path").unwrap_or_else(|| DEFAULT_LOCK_FILE_PATH.to_string());
+        let api_keys_file_path = reader.read_string("api_keys_file_path").unwrap_or_else(|| DEFAULT_API_KEYS_FILE_PATH.to_string());
+        // Off by default so existing installs keep working without keys, but on once keys have been created
+        let is_authentication_enabled = reader.read_bool("is_authentication_enabled").unwrap_or_else(|| home_dir.join(&api_keys_file_path).exists());
+        let cors_allowed_origins = Self::read_non_empty_list(&mut reader, "cors_allowed_origins", DEFAULT_CORS_ALLOWED_ORIGINS);
+        let cors_allowed_methods: Vec<String> = Self::read_non_empty_list(&mut reader, "cors_allowed_methods", DEFAULT_CORS_ALLOWED_METHODS)
+            .iter().map(|method| method.to_uppercase()).collect();
+        let cors_allowed_headers = Self::read_non_empty_list(&mut reader, "cors_allowed_headers", DEFAULT_CORS_ALLOWED_HEADERS);
+        let idempotency_window_seconds = reader.read_u64("idempotency_window_seconds").unwrap_or(DEFAULT_IDEMPOTENCY_WINDOW_SECONDS);
+        if idempotency_window_seconds == 0 {
+            reader.add_error("idempotency_window_seconds", "must be greater than 0");
+        }
+        let is_fair_queueing_enabled = reader.read_bool("is_fair_queueing_enabled").unwrap_or(false);
+        // No limit unless the host sets one
+        let max_pending_orders_per_requester = reader.read_u64("max_pending_orders_per_requester");
+        if max_pending_orders_per_requester == Some(0) {
+            reader.add_error("max_pending_orders_per_requester", "must be greater than 0");
+        }
+        let schedules_file_path = reader.read_string("schedules_file_path").unwrap_or_else(|| DEFAULT_SCHEDULES_FILE_PATH.to_string());
+        let cleaning_programs_file_path = reader.read_string("cleaning_programs_file_path").unwrap_or_else(|| DEFAULT_CLEANING_PROGRAMS_FILE_PATH.to_string());
+        // 0 turns the warning off
+        let cleaning_due_after_idle_hours = reader.read_f32("cleaning_due_after_idle_hours").unwrap_or(DEFAULT_CLEANING_DUE_AFTER_IDLE_HOURS);
+        if cleaning_due_after_idle_hours < 0.0 {
+            reader.add_error("cleaning_due_after_idle_hours", "must be 0 or more");
+        }
+        let cleaning_due_after_ml = reader.read_f32("cleaning_due_after_ml").unwrap_or(DEFAULT_CLEANING_DUE_AFTER_ML);
+        if cleaning_due_after_ml < 0.0 {
+            reader.add_error("cleaning_due_after_ml", "must be 0 or more");
+        }
+        let replace_tubing_threshold = Self::read_maintenance_threshold(&mut reader, "replace_tubing", MaintenanceThreshold {
+            after_ml: Some(DEFAULT_REPLACE_TUBING_AFTER_ML),
+            ..MaintenanceThreshold::default()
+        });
+        let replace_pump_threshold = Self::read_maintenance_threshold(&mut reader, "replace_pump", MaintenanceThreshold {
+            after_on_hours: Some(DEFAULT_REPLACE_PUMP_AFTER_ON_HOURS),
+            ..MaintenanceThreshold::default()
+        });
+        let tls_certificate_file_path = reader.read_string("tls_certificate_file_path").filter(|file_path| !file_path.trim().is_empty());
+        let tls_key_file_path = reader.read_string("tls_key_file_path").filter(|file_path| !file_path.trim().is_empty());
+        match (&tls_certificate_file_path, &tls_key_file_path) {
+            (Some(_), None) => reader.add_error("tls_key_file_path", "is required when tls_certificate_file_path is set"),
+            (None, Some(_)) => reader.add_error("tls_certificate_file_path", "is required when tls_key_file_path is set"),
+            _ => {}
+        }
+
+        match (ordered_pump_pin_numbers, milliseconds_per_ml, is_relay_inverted) {
+            (Some(ordered_pump_pin_numbers), Some(milliseconds_per_ml), Some(is_relay_inverted)) if reader.errors.is_empty() => Ok(Config {
+                rpi_chip_name,
+                ordered_pump_pin_numbers,
+                direction_pin_numbers,
+                milliseconds_per_ml,
+                is_relay_inverted,
+                pump_lines,
+                pumps_file_path: home_dir.join(pumps_file_path),
+                #[cfg(feature = "bff")]
+                settings_file_path: home_dir.join(settings_file_path),
+                #[cfg(feature = "bff")]
+                guests_file_path: home_dir.join(guests_file_path),
+                #[cfg(feature = "bff")]
+                consumption_limits,
+                #[cfg(feature = "bff")]
+                standard_drink_grams,
+                #[cfg(feature = "bff")]
+                shot_ml,
+                units_of_measure,
+                #[cfg(feature = "bff")]
+                default_cup_fill_ratio,
+                #[cfg(feature = "bff")]
+                cup_overflow_policy,
+                strings_xml_file_path: home_dir.join(strings_xml_file_path),
+                default_locale,
+                log_locale,
+                lock_file_path: home_dir.join(lock_file_path),
+                is_authentication_enabled,
+                api_keys_file_path: home_dir.join(api_keys_file_path),
+                cors_allowed_origins,
+                cors_allowed_methods,
+                cors_allowed_headers,
+                idempotency_window_seconds,
+                is_fair_queueing_enabled,
+                max_pending_orders_per_requester: max_pending_orders_per_requester.map(|max_pending_orders| max_pending_orders as u32),
+                schedules_file_path: home_dir.join(schedules_file_path),
+                cleaning_programs_file_path: home_dir.join(cleaning_programs_file_path),
+                cleaning_due_after_idle_hours: Some(cleaning_due_after_idle_hours).filter(|idle_hours| *idle_hours > 0.0),
+                cleaning_due_after_ml: Some(cleaning_due_after_ml).filter(|ml| *ml > 0.0),
+                replace_tubing_threshold,
+                replace_pump_threshold,
+                tls_certificate_file_path: tls_certificate_file_path.map(|file_path| home_dir.join(file_path)),
+                tls_key_file_path: tls_key_file_path.map(|file_path| home_dir.join(file_path))
+            }),
+            _ => Err(reader.errors)
+        }
+    }
+
+    fn read_non_empty_list(reader: &mut ConfigReader, key: &str, default_items: &[&str]) -> Vec<String> {
+        let items = reader.read_string_list(key).unwrap_or_else(|| default_items.iter().map(|item| item.to_string()).collect());
+        if items.is_empty() {
+            reader.add_error(key, "must contain at least one entry");
+        }
+        items
+    }
+
+    // Reads <task>_after_on_hours, <task>_after_switch_cycles and <task>_after_ml, where 0 turns a limit off
+    fn read_maintenance_threshold(reader: &mut ConfigReader, task_key: &str, default_threshold: MaintenanceThreshold) -> MaintenanceThreshold {
+        let on_hours_key = format!("{}_after_on_hours", task_key);
+        let after_on_hours = reader.read_f32(&on_hours_key).or(default_threshold.after_on_hours).unwrap_or(0.0);
+        if after_on_hours < 0.0 {
+            reader.add_error(&on_hours_key, "must be 0 or more");
+        }
+        let after_switch_cycles = reader.read_u64(&format!("{}_after_switch_cycles", task_key)).or(default_threshold.after_switch_cycles).unwrap_or(0);
+        let ml_key = format!("{}_after_ml", task_key);
+        let after_ml = reader.read_f32(&ml_key).or(default_threshold.after_ml).unwrap_or(0.0);
+        if after_ml < 0.0 {
+            reader.add_error(&ml_key, "must be 0 or more");
+        }
+        MaintenanceThreshold {
+            after_on_hours: Some(after_on_hours).filter(|on_hours| *on_hours > 0.0),
+            after_switch_cycles: Some(after_switch_cycles).filter(|switch_cycles| *switch_cycles > 0),
+            after_ml: Some(after_ml).filter(|ml| *ml > 0.0)
+        }
+    }
+
+    // One value for every pump from the per pump list if there is one, otherwise the value for all of them
+    fn read_per_pump_f32(reader: &mut ConfigReader, key: &str, per_pump_key: &str, number_of_pumps: usize, default_value: f32) -> Vec<f32> {
+        let value = reader.read_f32(key).unwrap_or(default_value);
+        match reader.read_f32_list(per_pump_key) {
+            Some(values) if values.len() == number_of_pumps => values,
+            Some(values) => {
+                reader.add_error(per_pump_key, &format!("expected a value for each of the {} pumps but got {}", number_of_pumps, values.len()));
+                vec![value; number_of_pumps]
+            },
+            None => vec![value; number_of_pumps]
+        }
+    }
+
+    // Configured units are added to the defaults, or replace the default of the same name
+    fn read_units_of_measure(reader: &mut ConfigReader, key: &str, shot_ml: f32) -> Vec<UnitOfMeasure> {
+        let mut units_of_measure: Vec<UnitOfMeasure> = DEFAULT_UNITS_OF_MEASURE.iter()
+            .map(|(name, ml)| UnitOfMeasure { name: name.to_string(), ml: *ml })
+            .chain(std::iter::once(UnitOfMeasure { name: "shot".to_string(), ml: shot_ml }))
+            .collect();
+        for unit_of_measure in reader.read_units_of_measure(key).unwrap_or_default() {
+            if unit_of_measure.name.is_empty() || unit_of_measure.ml <= 0.0 {
+                reader.add_error(key, &format!("\"{}\" must have a name and be greater than 0 ml", unit_of_measure.name));
+                continue;
+            }
+            units_of_measure.retain(|existing_unit| existing_unit.name != unit_of_measure.name);
+            units_of_measure.push(unit_of_measure);
+        }
+        units_of_measure
+    }
+
+    fn validate_pin_numbers(reader: &mut ConfigReader, key: &str, pin_numbers: &[u32]) {
+        if pin_numbers.is_empty() {
+            reader.add_error(key, "must contain at least one pin number");
+        }
+        if pin_numbers.len() > u8::MAX as usize {
+            reader.add_error(key, &format!("must contain at most {} pin numbers", u8::MAX));
+        }
+        let mut seen_pin_numbers = HashSet::new();
+        for pin_number in pin_numbers {
+            if !seen_pin_numbers.insert(pin_number) {
+                reader.add_error(key, &format!("pin {} is used more than once", pin_number));
+            }
+        }
+    }
+}
+
+// Reads keys from the config file, letting an environment variable named after the upper-cased key take precedence
+struct ConfigReader {
+    table: Table,
+    errors: Vec<ConfigError>
+}
+
+impl ConfigReader {
+    fn new() -> ConfigReader {
+        ConfigReader { table: Table::new(), errors: vec![] }
+    }
+
+    fn load_file(&mut self, file_path: PathBuf) {
+        // The config file is optional so existing dotenv-only setups keep working
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(_) => return
+        };
+        match content.parse::<Value>() {
+            Ok(Value::Table(table)) => self.table = table,
+            Ok(_) => self.add_error(CONFIG_FILE_PATH_KEY, &format!("{} is not a table", file_path.display())),
+            Err(error) => self.add_error(CONFIG_FILE_PATH_KEY, &format!("couldn't parse {}: {}", file_path.display(), error))
+        }
+    }
+
+    fn add_error(&mut self, key: &str, message: &str) {
+        self.errors.push(ConfigError { key: key.to_string(), message: message.to_string() });
+    }
+
+    fn add_missing_error(&mut self, key: &str) {
+        // A value that failed to parse has already been reported
+        if !self.errors.iter().any(|error| error.key == key) {
+            self.add_error(key, "is required");
+        }
+    }
+
+    fn read<T>(&mut self, key: &str, parse_env: fn(&str) -> Result<T, String>, parse_toml: fn(&Value) -> Result<T, String>) -> Option<T> {
+        let result = match dotenv::var(key.to_uppercase()) {
+            Ok(env_value) => parse_env(env_value.trim()),
+            Err(_) => match self.table.get(key) {
+                Some(toml_value) => parse_toml(toml_value),
+                None => return None
+            }
+        };
+        match result {
+            Ok(value) => Some(value),
+            Err(message) => {
+                self.add_error(key, &message);
+                None
+            }
+        }
+    }
+
+    fn read_string(&mut self, key: &str) -> Option<String> {
+        self.read(
+            key,
+            |env_value| Ok(env_value.to_string()),
+            |toml_value| toml_value.as_str().map(|value| value.to_string()).ok_or_else(|| "expected a string".to_string())
+        )
+    }
+
+    fn read_u64(&mut self, key: &str) -> Option<u64> {
+        self.read(
+            key,
+            |env_value| env_value.parse::<u64>().map_err(|_| format!("expected a non-negative integer but got \"{}\"", env_value)),
+            |toml_value| match toml_value.as_integer() {
+                Some(integer) if integer >= 0 => Ok(integer as u64),
+                _ => Err("expected a non-negative integer".to_string())
+            }
+        )
+    }
+
+    fn read_f32(&mut self, key: &str) -> Option<f32> {
+        self.read(
+            key,
+            |env_value| env_value.parse::<f32>().map_err(|_| format!("expected a number but got \"{}\"", env_value)),
+            |toml_value| toml_value.as_float().or_else(|| toml_value.as_integer().map(|integer| integer as f64))
+                .map(|float| float as f32)
+                .ok_or_else(|| "expected a number".to_string())
+        )
+    }
+
+    fn read_f32_list(&mut self, key: &str) -> Option<Vec<f32>> {
+        self.read(
+            key,
+            |env_value| env_value.split(',')
+                .map(|item| item.trim().parse::<f32>().map_err(|_| format!("expected a comma separated list of numbers but got \"{}\"", item.trim())))
+                .collect(),
+            |toml_value| match toml_value.as_array() {
+                Some(items) => items.iter()
+                    .map(|item| item.as_float().or_else(|| item.as_integer().map(|integer| integer as f64))
+                        .map(|float| float as f32)
+                        .ok_or_else(|| format!("expected a list of numbers but found {}", item)))
+                    .collect(),
+                None => Err("expected a list of numbers".to_string())
+            }
+        )
+    }
+
+    fn read_bool(&mut self, key: &str) -> Option<bool> {
+        self.read(
+            key,
+            |env_value| match env_value.to_lowercase().as_str() {
+                "1" | "true" => Ok(true),
+                "0" | "false" => Ok(false),
+                _ => Err(format!("expected 1, 0, true or false but got \"{}\"", env_value))
+            },
+            |toml_value| toml_value.as_bool().ok_or_else(|| "expected a boolean".to_string())
+        )
+    }
+
+    fn read_string_list(&mut self, key: &str) -> Option<Vec<String>> {
+        self.read(
+            key,
+            |env_value| Ok(env_value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect()),
+            |toml_value| match toml_value.as_array() {
+                Some(items) => items.iter()
+                    .map(|item| item.as_str().map(|item| item.trim().to_string()).ok_or_else(|| format!("expected a list of strings but found {}", item)))
+                    .collect(),
+                None => Err("expected a list of strings".to_string())
+            }
+        )
+    }
+
+    fn read_units_of_measure(&mut self, key: &str) -> Option<Vec<UnitOfMeasure>> {
+        self.read(
+            key,
+            |env_value| env_value.split(',')
+                .map(|item| match item.split_once(':') {
+                    Some((name, ml)) => ml.trim().parse::<f32>().ok().map(|ml| UnitOfMeasure { name: UnitsOfMeasure::normalize_name(name), ml }),
+                    None => None
+                }.ok_or_else(|| format!("expected a comma separated list of <unit>:<ml> but got \"{}\"", item.trim())))
+                .collect(),
+            |toml_value| match toml_value.as_table() {
+                Some(table) => table.iter()
+                    .map(|(name, ml)| ml.as_float().or_else(|| ml.as_integer().map(|integer| integer as f64))
+                        .map(|ml| UnitOfMeasure { name: UnitsOfMeasure::normalize_name(name), ml: ml as f32 })
+                        .ok_or_else(|| format!("expected a number of ml for \"{}\" but found {}", name, ml)))
+                    .collect(),
+                None => Err("expected a table of units and their ml".to_string())
+            }
+        )
+    }
+
+    #[cfg(feature = "bff")]
+    fn read_consumption_limits(&mut self, key: &str) -> Option<Vec<ConsumptionLimit>> {
+        self.read(
+            key,
+            |env_value| env_value.split(',')
+                .map(|item| {
+                    let parts: Vec<&str> = item.split(':').map(|part| part.trim()).collect();
+                    match parts.as_slice() {
+                        [window_minutes, max_alcohol_grams] => Self::create_consumption_limit(window_minutes.parse::<i64>().ok(), max_alcohol_grams.parse::<f64>().ok()),
+                        _ => None
+                    }.ok_or_else(|| format!("expected a comma separated list of <window minutes>:<max alcohol grams> but got \"{}\"", item.trim()))
+                })
+                .collect(),
+            |toml_value| match toml_value.as_array() {
+                Some(items) => items.iter()
+                    .map(|item| Self::create_consumption_limit(
+                        item.get("window_minutes").and_then(|value| value.as_integer()),
+                        item.get("max_alcohol_grams").and_then(|value| value.as_float().or_else(|| value.as_integer().map(|integer| integer as f64)))
+                    ).ok_or_else(|| format!("expected a table with a positive window_minutes and max_alcohol_grams but found {}", item)))
+                    .collect(),
+                None => Err("expected a list of tables".to_string())
+            }
+        )
+    }
+
+    #[cfg(feature = "bff")]
+    fn create_consumption_limit(window_minutes: Option<i64>, max_alcohol_grams: Option<f64>) -> Option<ConsumptionLimit> {
+        match (window_minutes, max_alcohol_grams) {
+            (Some(window_minutes), Some(max_alcohol_grams)) if window_minutes > 0 && max_alcohol_grams >= 0.0 => Some(ConsumptionLimit {
+                window_minutes: window_minutes as u64,
+                max_alcohol_grams: max_alcohol_grams as f32
+            }),
+            _ => None
+        }
+    }
+
+    fn read_u32_list(&mut self, key: &str) -> Option<Vec<u32>> {
+        self.read(
+            key,
+            |env_value| env_value.split(',')
+                .map(|item| item.trim().parse::<u32>().map_err(|_| format!("expected a comma separated list of pin numbers but got \"{}\"", item.trim())))
+                .collect(),
+            |toml_value| match toml_value.as_array() {
+                Some(items) => items.iter()
+                    .map(|item| match item.as_integer() {
+                        Some(integer) if integer >= 0 && integer <= u32::MAX as i64 => Ok(integer as u32),
+                        _ => Err(format!("expected a list of pin numbers but found {}", item))
+                    })
+                    .collect(),
+                None => Err("expected a list of pin numbers".to_string())
+            }
+        )
+    }
+}
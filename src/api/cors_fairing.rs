@@ -0,0 +1,96 @@
+use rocket::{ Request, Response };
+use rocket::fairing::{ Fairing, Info, Kind };
+use rocket::http::{ Header, Method };
+use rocket::request::{ FromRequest, Outcome };
+
+const ANY_ORIGIN: &str = "*";
+
+pub struct CorsFairing {
+    allowed_origins: Vec<String>,
+    allowed_methods: Vec<String>,
+    allowed_headers: Vec<String>
+}
+
+impl CorsFairing {
+    pub fn new(allowed_origins: Vec<String>, allowed_methods: Vec<String>, allowed_headers: Vec<String>) -> CorsFairing {
+        CorsFairing { allowed_origins, allowed_methods, allowed_headers }
+    }
+
+    // Methods of every mounted route whose path matches the request, ignoring the preflight route itself
+    pub fn get_route_methods(request: &Request<'_>) -> Vec<Method> {
+        let request_segments: Vec<&str> = request.uri().path().segments().collect();
+        let mut route_methods = vec![];
+        for route in request.rocket().routes() {
+            if route.method != Method::Options && !route_methods.contains(&route.method) && Self::path_matches(route.uri.path(), &request_segments) {
+                route_methods.push(route.method);
+            }
+        }
+        route_methods
+    }
+
+    fn path_matches(route_path: &str, request_segments: &[&str]) -> bool {
+        let route_segments: Vec<&str> = route_path.split('/').filter(|segment| !segment.is_empty()).collect();
+        for (index, route_segment) in route_segments.iter().enumerate() {
+            if route_segment.starts_with('<') && route_segment.ends_with("..>") {
+                return true;
+            }
+            match request_segments.get(index) {
+                Some(_) if route_segment.starts_with('<') => {},
+                Some(request_segment) if request_segment == route_segment => {},
+                _ => return false
+            }
+        }
+        route_segments.len() == request_segments.len()
+    }
+
+    fn get_allowed_origin(&self, origin: Option<&str>) -> Option<String> {
+        if self.allowed_origins.iter().any(|allowed_origin| allowed_origin == ANY_ORIGIN) {
+            return Some(ANY_ORIGIN.to_string());
+        }
+        origin.filter(|origin| self.allowed_origins.iter().any(|allowed_origin| allowed_origin.eq_ignore_ascii_case(origin)))
+            .map(|origin| origin.to_string())
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for CorsFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Attaching CORS headers to responses",
+            kind: Kind::Response
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let allowed_origin = self.get_allowed_origin(request.headers().get_one("Origin"));
+        if allowed_origin.as_deref() != Some(ANY_ORIGIN) {
+            // The answer depends on the origin so caches must not share it
+            response.adjoin_header(Header::new("Vary", "Origin"));
+        }
+        let allowed_origin = match allowed_origin {
+            Some(allowed_origin) => allowed_origin,
+            None => return
+        };
+        response.set_header(Header::new("Access-Control-Allow-Origin", allowed_origin));
+        if request.method() == Method::Options {
+            let allowed_methods: Vec<&str> = Self::get_route_methods(request).iter()
+                .map(|method| method.as_str())
+                .filter(|method| self.allowed_methods.iter().any(|allowed_method| allowed_method == method))
+                .collect();
+            response.set_header(Header::new("Access-Control-Allow-Methods", allowed_methods.join(", ")));
+            response.set_header(Header::new("Access-Control-Allow-Headers", self.allowed_headers.join(", ")));
+        }
+    }
+}
+
+// Lets the preflight route find out whether anything is mounted at the requested path
+pub struct RouteMethods(pub Vec<Method>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RouteMethods {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RouteMethods(CorsFairing::get_route_methods(request)))
+    }
+}
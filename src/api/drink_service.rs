@@ -0,0 +1,142 @@
+use std::sync::Arc;
+use serde_json::json;
+use uuid::Uuid;
+use crate::api::models::{ ApiError, ApiErrorCode, CupCapacity, CupOverflowPolicy, DrinkStrength, IngredientAdjustment, MeasuredIngredient, MenuDrink, PourModifiers, SizePreset };
+use crate::api::models::settings::{ Cup, Drink, Ingredient, Settings };
+use crate::api::SettingsService;
+
+const ETHANOL_GRAMS_PER_ML: f32 = 0.789;
+const EXTRA_PARTS_MULTIPLIER: f32 = 1.5;
+const MAX_STRENGTH: f32 = 3.0;
+
+pub struct DrinkService {
+    settings_service: Arc<SettingsService>,
+    standard_drink_grams: f32,
+    shot_ml: f32,
+    default_cup_fill_ratio: f32,
+    cup_overflow_policy: CupOverflowPolicy
+}
+
+impl DrinkService {
+    pub fn new(settings_service: Arc<SettingsService>, standard_drink_grams: f32, shot_ml: f32, default_cup_fill_ratio: f32, cup_overflow_policy: CupOverflowPolicy) -> DrinkService {
+        DrinkService { settings_service, standard_drink_grams, shot_ml, default_cup_fill_ratio, cup_overflow_policy }
+    }
+
+    // Every drink with its strength in the requested cup, or its default cup if none was requested
+    pub fn get_menu(&self, cup_id: Option<Uuid>) -> Result<Vec<MenuDrink>, ApiError> {
+        let settings = self.settings_service.settings.read().unwrap().clone();
+        let requested_cup = match cup_id {
+            Some(cup_id) => Some(Self::find_cup(&settings, cup_id)?),
+            None => None
+        };
+        Ok(settings.drinks.iter()
+            .map(|drink| {
+                let cup = requested_cup.or_else(|| drink.default_cup_id.and_then(|default_cup_id| Self::find_cup(&settings, default_cup_id).ok()));
+                let strength = cup.and_then(|cup| self.get_strength(&settings, drink, cup, &PourModifiers::default()).ok());
+                MenuDrink { drink: drink.clone(), strength }
+            })
+            .collect())
+    }
+
+    pub fn get_strength(&self, settings: &Settings, drink: &Drink, cup: &Cup, pour_modifiers: &PourModifiers) -> Result<DrinkStrength, ApiError> {
+        let measured_ingredients = self.measure_ingredients(settings, drink, cup, pour_modifiers)?;
+        let total_volume_ml: f32 = measured_ingredients.iter().map(|measured_ingredient| measured_ingredient.ml).sum();
+        let alcohol_grams: f32 = measured_ingredients.iter().map(|measured_ingredient| measured_ingredient.alcohol_grams).sum();
+        let abv = if total_volume_ml > 0.0 { alcohol_grams / ETHANOL_GRAMS_PER_ML / total_volume_ml * 100.0 } else { 0.0 };
+        Ok(DrinkStrength {
+            cup_id: cup.id,
+            total_volume_ml: Self::round(total_volume_ml),
+            abv: Self::round(abv),
+            alcohol_grams: Self::round(alcohol_grams),
+            standard_drinks: Self::round(alcohol_grams / self.standard_drink_grams)
+        })
+    }
+
+    // Splits what fits in the cup between the drink's ingredients by their parts, then resizes the alcoholic ones as asked and
+    // rebalances the rest so the drink still fills the same volume
+    pub fn measure_ingredients(&self, settings: &Settings, drink: &Drink, cup: &Cup, pour_modifiers: &PourModifiers) -> Result<Vec<MeasuredIngredient>, ApiError> {
+        Self::validate_pour_modifiers(drink, pour_modifiers)?;
+        let mut weighted_ingredients = vec![];
+        for ingredient_measurement in &drink.ingredient_measurements {
+            let adjustment = pour_modifiers.ingredients.iter()
+                .find(|ingredient_modifier| ingredient_modifier.ingredient_id == ingredient_measurement.ingredient_id)
+                .map(|ingredient_modifier| ingredient_modifier.adjustment);
+            let parts = match adjustment {
+                Some(IngredientAdjustment::Extra) => ingredient_measurement.parts as f32 * EXTRA_PARTS_MULTIPLIER,
+                Some(IngredientAdjustment::Omit) => 0.0,
+                None => ingredient_measurement.parts as f32
+            };
+            if parts > 0.0 {
+                let ingredient = settings.ingredients.iter().find(|ingredient| ingredient.id == ingredient_measurement.ingredient_id);
+                weighted_ingredients.push((ingredient_measurement.ingredient_id, ingredient, parts));
+            }
+        }
+        let total_parts: f32 = weighted_ingredients.iter().map(|(_, _, parts)| parts).sum();
+        if total_parts <= 0.0 {
+            return Err(ApiError::new(ApiErrorCode::DrinkHasNoIngredients));
+        }
+        let is_alcoholic = |ingredient: &Option<&Ingredient>| ingredient.is_some_and(|ingredient| ingredient.is_alcoholic);
+        let alcoholic_parts: f32 = weighted_ingredients.iter().filter(|(_, ingredient, _)| is_alcoholic(ingredient)).map(|(_, _, parts)| parts).sum();
+        let mixer_parts = total_parts - alcoholic_parts;
+        let target_ml = match pour_modifiers.size {
+            SizePreset::Shot => self.shot_ml,
+            _ => self.get_cup_capacity(cup).capacity_ml
+        };
+        let base_alcoholic_ml = match pour_modifiers.size {
+            SizePreset::Single if alcoholic_parts > 0.0 => self.shot_ml,
+            SizePreset::Double if alcoholic_parts > 0.0 => self.shot_ml * 2.0,
+            _ => target_ml * alcoholic_parts / total_parts
+        };
+        let alcoholic_ml = (base_alcoholic_ml * pour_modifiers.strength).min(target_ml);
+        let mixer_ml = if mixer_parts > 0.0 { target_ml - alcoholic_ml } else { 0.0 };
+        Ok(weighted_ingredients.iter()
+            .map(|(ingredient_id, ingredient, parts)| {
+                let ml = match is_alcoholic(ingredient) {
+                    true => alcoholic_ml * parts / alcoholic_parts,
+                    false => mixer_ml * parts / mixer_parts
+                };
+                MeasuredIngredient {
+                    ingredient_id: *ingredient_id,
+                    ml,
+                    alcohol_grams: Self::get_alcohol_grams(ml, ingredient.map_or(0.0, |ingredient| ingredient.abv))
+                }
+            })
+            .collect())
+    }
+
+    pub fn get_cup_capacity(&self, cup: &Cup) -> CupCapacity {
+        CupCapacity {
+            cup_id: cup.id,
+            capacity_ml: cup.volume_ml as f32 * cup.fill_ratio.unwrap_or(self.default_cup_fill_ratio),
+            overflow_policy: self.cup_overflow_policy
+        }
+    }
+
+    pub fn find_cup_capacity(&self, cup_id: Uuid) -> Result<CupCapacity, ApiError> {
+        let settings = self.settings_service.settings.read().unwrap();
+        Ok(self.get_cup_capacity(Self::find_cup(&settings, cup_id)?))
+    }
+
+    fn validate_pour_modifiers(drink: &Drink, pour_modifiers: &PourModifiers) -> Result<(), ApiError> {
+        let is_strength_valid = pour_modifiers.strength > 0.0 && pour_modifiers.strength <= MAX_STRENGTH;
+        let are_ingredients_in_drink = pour_modifiers.ingredients.iter().all(|ingredient_modifier| drink.ingredient_measurements.iter()
+            .any(|ingredient_measurement| ingredient_measurement.ingredient_id == ingredient_modifier.ingredient_id));
+        if !is_strength_valid || !are_ingredients_in_drink {
+            return Err(ApiError::with_details(ApiErrorCode::PourModifiersInvalid, json!({ "max_strength": MAX_STRENGTH })));
+        }
+        Ok(())
+    }
+
+    pub fn get_alcohol_grams(ml: f32, abv: f32) -> f32 {
+        ml * abv / 100.0 * ETHANOL_GRAMS_PER_ML
+    }
+
+    fn find_cup(settings: &Settings, cup_id: Uuid) -> Result<&Cup, ApiError> {
+        settings.cups.iter().find(|cup| cup.id == cup_id)
+            .ok_or_else(|| ApiError::with_details(ApiErrorCode::CupNotFound, json!({ "cup_id": cup_id })))
+    }
+
+    fn round(value: f32) -> f32 {
+        (value * 10.0).round() / 10.0
+    }
+}
@@ -0,0 +1,154 @@
+use crate::api::models::resources_xml::ResourcesElement;
+
+pub struct FluentConverter {}
+
+impl FluentConverter {
+    // Returns the .ftl source along with every string that couldn't be converted and was left out
+    pub fn convert(resource_element: &ResourcesElement) -> (String, Vec<String>) {
+        let mut ftl = String::from("# Converted from a strings.xml file\n\n");
+        let mut errors = vec![];
+        for string_element in &resource_element.strings {
+            if !Self::is_valid_identifier(&string_element.name) {
+                errors.push(format!("\"{}\" isn't a valid Fluent message id", string_element.name));
+                continue;
+            }
+            match Self::convert_content(&string_element.content) {
+                Ok(pattern) => ftl.push_str(&format!("{} = {}\n", string_element.name, pattern)),
+                Err(error) => errors.push(format!("Couldn't convert \"{}\": {}", string_element.name, error))
+            }
+        }
+        (ftl, errors)
+    }
+
+    // Turns handlebars variables like {{pump_number}} into Fluent variables like { $pump_number }
+    fn convert_content(content: &str) -> Result<String, String> {
+        if content.is_empty() {
+            return Ok("{\"\"}".to_string());
+        }
+        let middle = content.trim();
+        if middle.is_empty() {
+            return Ok(Self::to_string_literal(content));
+        }
+        let leading = &content[..content.len() - content.trim_start().len()];
+        let trailing = &content[content.trim_end().len()..];
+        let mut pattern = String::new();
+        // Fluent trims surrounding whitespace so it's kept as string literals
+        if !leading.is_empty() {
+            pattern.push_str(&Self::to_string_literal(leading));
+        }
+        let mut rest = middle;
+        while !rest.is_empty() {
+            if let Some(after_open) = rest.strip_prefix("{{") {
+                let close_index = after_open.find("}}").ok_or_else(|| "unclosed \"{{\"".to_string())?;
+                let expression = after_open[..close_index].trim();
+                if !Self::is_valid_identifier(expression) {
+                    return Err(format!("\"{{{{{}}}}}\" is more than a plain variable", expression));
+                }
+                pattern.push_str(&format!("{{ ${} }}", expression));
+                rest = &after_open[close_index + 2..];
+                continue;
+            }
+            let mut characters = rest.chars();
+            match characters.next() {
+                // Braces are syntax in Fluent so literal ones become string literals
+                Some(character) if character == '{' || character == '}' => pattern.push_str(&Self::to_string_literal(&character.to_string())),
+                // Continuation lines must be indented
+                Some('\n') => pattern.push_str("\n    "),
+                Some(character) => pattern.push(character),
+                None => {}
+            }
+            rest = characters.as_str();
+        }
+        if !trailing.is_empty() {
+            pattern.push_str(&Self::to_string_literal(trailing));
+        }
+        Ok(pattern)
+    }
+
+    fn to_string_literal(text: &str) -> String {
+        let mut literal = String::from("{\"");
+        for character in text.chars() {
+            match character {
+                '"' => literal.push_str("\\\""),
+                '\\' => literal.push_str("\\\\"),
+                character if character.is_control() => literal.push_str(&format!("\\u{:04X}", character as u32)),
+                character => literal.push(character)
+            }
+        }
+        literal.push_str("\"}");
+        literal
+    }
+
+    fn is_valid_identifier(identifier: &str) -> bool {
+        let mut characters = identifier.chars();
+        match characters.next() {
+            Some(first) if first.is_ascii_alphabetic() => characters.all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '-'),
+            _ => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hard_xml::XmlRead;
+    use serde_json::{ Map, Value };
+    use crate::api::models::resources_xml::ResourcesElement;
+    use crate::api::{ FluentResourceCatalog, ResourceCatalog, XmlResourceCatalog };
+    use super::FluentConverter;
+
+    const STRINGS_XML: &str = include_str!("../../resources/strings.xml");
+
+    fn get_placeholder_names(content: &str) -> Vec<String> {
+        content.split("{{").skip(1)
+            .filter_map(|after_open| after_open.split_once("}}").map(|(expression, _)| expression.trim().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn converts_every_default_string() {
+        let resource_element = ResourcesElement::from_str(STRINGS_XML).unwrap();
+        let (_, errors) = FluentConverter::convert(&resource_element);
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    // Every string renders the same from the converted .ftl as from the strings.xml it came from
+    #[test]
+    fn round_trips_default_strings() {
+        let resource_element = ResourcesElement::from_str(STRINGS_XML).unwrap();
+        let (ftl, _) = FluentConverter::convert(&resource_element);
+        let (fluent_catalog, fluent_errors) = FluentResourceCatalog::new("en", ftl);
+        assert_eq!(fluent_errors, Vec::<String>::new());
+        let contents: Vec<(String, String)> = resource_element.strings.iter().map(|string_element| (string_element.name.clone(), string_element.content.clone())).collect();
+        let (xml_catalog, xml_errors) = XmlResourceCatalog::new(resource_element);
+        assert_eq!(xml_errors, Vec::<String>::new());
+        for (name, content) in contents {
+            let data: Map<String, Value> = get_placeholder_names(&content).into_iter()
+                .map(|placeholder_name| (placeholder_name.clone(), Value::String(format!("<{}>", placeholder_name))))
+                .collect();
+            let data = Value::Object(data);
+            assert_eq!(fluent_catalog.render(&name, &data), xml_catalog.render(&name, &data), "{} doesn't round trip", name);
+        }
+    }
+
+    #[test]
+    fn converts_placeholders_to_variables() {
+        assert_eq!(FluentConverter::convert_content("Pump {{pump_number}} on pin {{ pin_number }}").unwrap(), "Pump { $pump_number } on pin { $pin_number }");
+    }
+
+    #[test]
+    fn quotes_braces_and_surrounding_whitespace() {
+        assert_eq!(FluentConverter::convert_content(" {a} ").unwrap(), "{\" \"}{\"{\"}a{\"}\"}{\" \"}");
+        assert_eq!(FluentConverter::convert_content("").unwrap(), "{\"\"}");
+    }
+
+    #[test]
+    fn indents_continuation_lines() {
+        assert_eq!(FluentConverter::convert_content("one\ntwo").unwrap(), "one\n    two");
+    }
+
+    #[test]
+    fn rejects_helpers_and_unclosed_placeholders() {
+        assert!(FluentConverter::convert_content("{{#if cup}}full{{/if}}").is_err());
+        assert!(FluentConverter::convert_content("Pump {{pump_number").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use fluent_bundle::{ FluentArgs, FluentResource, FluentValue };
+use fluent_bundle::concurrent::FluentBundle;
+use serde_json::Value;
+use unic_langid::LanguageIdentifier;
+use crate::api::ResourceCatalog;
+
+// Strings from a Project Fluent (.ftl) file, which can express plurals and number formatting
+pub struct FluentResourceCatalog {
+    bundle: FluentBundle<FluentResource>
+}
+
+impl FluentResourceCatalog {
+    // Returns the catalog along with every problem found; messages that did parse are still usable
+    pub fn new(locale: &str, source: String) -> (FluentResourceCatalog, Vec<String>) {
+        let mut errors = vec![];
+        let language_identifier = locale.parse::<LanguageIdentifier>().unwrap_or_else(|_| {
+            errors.push(format!("\"{}\" isn't a valid locale for plural rules", locale));
+            LanguageIdentifier::default()
+        });
+        let mut bundle = FluentBundle::new_concurrent(vec![language_identifier]);
+        // Isolation marks would end up in log files and JSON bodies
+        bundle.set_use_isolating(false);
+        let resource = match FluentResource::try_new(source) {
+            Ok(resource) => resource,
+            Err((resource, parser_errors)) => {
+                errors.extend(parser_errors.iter().map(|parser_error| format!("Couldn't parse {:?}", parser_error)));
+                resource
+            }
+        };
+        if let Err(bundle_errors) = bundle.add_resource(resource) {
+            errors.extend(bundle_errors.iter().map(|bundle_error| bundle_error.to_string()));
+        }
+        (FluentResourceCatalog { bundle }, errors)
+    }
+
+    fn format(&self, name: &str, args: Option<&FluentArgs>) -> Option<String> {
+        let pattern = self.bundle.get_message(name)?.value()?;
+        let mut errors = vec![];
+        let formatted = self.bundle.format_pattern(pattern, args, &mut errors);
+        Some(formatted.into_owned())
+    }
+
+    fn to_fluent_args(data: &Value) -> FluentArgs<'_> {
+        let mut args = FluentArgs::new();
+        if let Value::Object(fields) = data {
+            for (key, value) in fields {
+                match value {
+                    Value::Number(number) => args.set(key.as_str(), FluentValue::from(number.as_f64().unwrap_or_default())),
+                    Value::String(string) => args.set(key.as_str(), FluentValue::from(string.as_str())),
+                    other => args.set(key.as_str(), FluentValue::from(other.to_string()))
+                }
+            }
+        }
+        args
+    }
+}
+
+impl ResourceCatalog for FluentResourceCatalog {
+    fn contains(&self, name: &str) -> bool {
+        self.bundle.has_message(name)
+    }
+
+    fn get_string(&self, name: &str) -> Option<String> {
+        self.format(name, None)
+    }
+
+    fn render(&self, name: &str, data: &Value) -> Option<String> {
+        self.format(name, Some(&Self::to_fluent_args(data)))
+    }
+}
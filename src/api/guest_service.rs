@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{ Arc, Mutex };
+use std::time::{ SystemTime, UNIX_EPOCH };
+use serde_json::json;
+use uuid::Uuid;
+use crate::api::models::{
+    ApiError,
+    ApiErrorCode,
+    Caller,
+    ConsumptionLimit,
+    ConsumptionRecord,
+    GuestProfile,
+    GuestProfileReport,
+    GuestsFile,
+    NewGuestProfile,
+    Role,
+    WindowConsumption
+};
+use crate::api::PumpService;
+
+// Alcohol the body breaks down per hour in percent blood alcohol
+const ELIMINATION_PERCENT_PER_HOUR: f32 = 0.015;
+const DEFAULT_WIDMARK_FACTOR: f32 = 0.6;
+// Records are kept at least this long so the blood alcohol estimate has them
+const MIN_RECORD_RETENTION_SECONDS: u64 = 24 * 60 * 60;
+
+pub struct GuestService {
+    guests_file: Mutex<GuestsFile>,
+    guests_file_path: PathBuf,
+    consumption_limits: Vec<ConsumptionLimit>,
+    // Tells what became of the orders, without it every order counts in full
+    pump_service: Option<Arc<Mutex<PumpService>>>
+}
+
+impl GuestService {
+    pub fn new(guests_file: GuestsFile, guests_file_path: PathBuf, consumption_limits: Vec<ConsumptionLimit>, pump_service: Option<Arc<Mutex<PumpService>>>) -> GuestService {
+        GuestService { guests_file: Mutex::new(guests_file), guests_file_path, consumption_limits, pump_service }
+    }
+
+    pub fn get_guests(&self) -> Vec<GuestProfile> {
+        self.guests_file.lock().unwrap().guests.clone()
+    }
+
+    pub fn create_guest(&self, new_guest_profile: NewGuestProfile) -> Result<GuestProfile, ApiError> {
+        let guest_profile = Self::create_guest_profile(Uuid::new_v4(), new_guest_profile)?;
+        let mut guests_file = self.guests_file.lock().unwrap();
+        guests_file.guests.push(guest_profile.clone());
+        self.save(&guests_file)?;
+        Ok(guest_profile)
+    }
+
+    pub fn update_guest(&self, id: Uuid, new_guest_profile: NewGuestProfile) -> Result<GuestProfile, ApiError> {
+        let guest_profile = Self::create_guest_profile(id, new_guest_profile)?;
+        let mut guests_file = self.guests_file.lock().unwrap();
+        let existing_guest_profile = guests_file.guests.iter_mut().find(|guest| guest.id == id)
+            .ok_or_else(|| Self::create_guest_not_found_error(id))?;
+        *existing_guest_profile = guest_profile.clone();
+        self.save(&guests_file)?;
+        Ok(guest_profile)
+    }
+
+    pub fn delete_guest(&self, id: Uuid) -> Result<(), ApiError> {
+        let mut guests_file = self.guests_file.lock().unwrap();
+        if !guests_file.guests.iter().any(|guest| guest.id == id) {
+            return Err(Self::create_guest_not_found_error(id));
+        }
+        guests_file.guests.retain(|guest| guest.id != id);
+        guests_file.consumption.retain(|record| record.guest_id != id);
+        self.save(&guests_file)
+    }
+
+    pub fn get_guest_report(&self, caller: &Caller, id: Uuid) -> Result<GuestProfileReport, ApiError> {
+        self.settle_orders();
+        let guests_file = self.guests_file.lock().unwrap();
+        let profile = guests_file.guests.iter().find(|guest| guest.id == id)
+            .ok_or_else(|| Self::create_guest_not_found_error(id))?;
+        Self::check_guest_access(caller, profile)?;
+        let now = Self::get_now();
+        let consumption = self.consumption_limits.iter()
+            .map(|consumption_limit| WindowConsumption {
+                window_minutes: consumption_limit.window_minutes,
+                alcohol_grams: Self::get_alcohol_grams_in_window(&guests_file.consumption, id, consumption_limit.window_minutes, now),
+                max_alcohol_grams: consumption_limit.max_alcohol_grams
+            })
+            .collect();
+        let estimated_blood_alcohol_percent = profile.body_weight_kg.map(|body_weight_kg| {
+            let widmark_factor = profile.widmark_factor.unwrap_or(DEFAULT_WIDMARK_FACTOR);
+            Self::estimate_blood_alcohol_percent(&guests_file.consumption, id, body_weight_kg, widmark_factor, now)
+        });
+        Ok(GuestProfileReport { profile: profile.clone(), consumption, estimated_blood_alcohol_percent })
+    }
+
+    // Works out who an order counts against; orders by bartenders and admins without a guest aren't counted
+    pub fn resolve_guest_id(&self, caller: &Caller, requested_guest_id: Option<Uuid>) -> Result<Option<Uuid>, ApiError> {
+        let guests_file = self.guests_file.lock().unwrap();
+        if let Some(requested_guest_id) = requested_guest_id {
+            let profile = guests_file.guests.iter().find(|guest| guest.id == requested_guest_id)
+                .ok_or_else(|| Self::create_guest_not_found_error(requested_guest_id))?;
+            Self::check_guest_access(caller, profile)?;
+            return Ok(Some(requested_guest_id));
+        }
+        let api_key_id = match caller.api_key_id {
+            Some(api_key_id) => api_key_id,
+            None => return Ok(None)
+        };
+        match guests_file.guests.iter().find(|guest| guest.api_key_id == Some(api_key_id)) {
+            Some(profile) => Ok(Some(profile.id)),
+            // A guest key without a profile is still a guest of its own
+            None if caller.role == Role::Guest => Ok(Some(api_key_id)),
+            None => Ok(None)
+        }
+    }
+
+    // Runs the pour only if its alcohol fits every limit and records it once the pour was accepted.
+    // The drink is reserved against the limits before pouring so the guests aren't locked while the pump queue is busy
+    pub fn pour_within_limits<T>(&self, guest_id: Option<Uuid>, order_id: Uuid, alcohol_grams: f32, pour: impl FnOnce() -> Result<T, ApiError>) -> Result<T, ApiError> {
+        let guest_id = match guest_id {
+            Some(guest_id) if alcohol_grams > 0.0 => guest_id,
+            _ => return pour()
+        };
+        let reserved_record = self.reserve_within_limits(guest_id, alcohol_grams)?;
+        let result = pour();
+        let mut guests_file = self.guests_file.lock().unwrap();
+        let reserved_index = guests_file.consumption.iter().rposition(|record| *record == reserved_record);
+        if result.is_err() {
+            if let Some(reserved_index) = reserved_index {
+                guests_file.consumption.remove(reserved_index);
+            }
+            return result;
+        }
+        // Only now that the order is queued can it be settled
+        if let Some(reserved_index) = reserved_index {
+            guests_file.consumption[reserved_index].order_id = Some(order_id);
+        }
+        let retention_seconds = self.consumption_limits.iter()
+            .map(|consumption_limit| consumption_limit.window_minutes * 60)
+            .fold(MIN_RECORD_RETENTION_SECONDS, u64::max);
+        let now = Self::get_now();
+        guests_file.consumption.retain(|record| record.poured_at + retention_seconds > now);
+        // The drink is already queued so failing to persist it only loses history
+        if let Err(error) = self.save(&guests_file) {
+            log::warn!("Couldn't save consumption for guest {}: {:?}", guest_id, error.details);
+        }
+        result
+    }
+
+    fn reserve_within_limits(&self, guest_id: Uuid, alcohol_grams: f32) -> Result<ConsumptionRecord, ApiError> {
+        self.settle_orders();
+        let mut guests_file = self.guests_file.lock().unwrap();
+        let now = Self::get_now();
+        for consumption_limit in &self.consumption_limits {
+            let consumed_alcohol_grams = Self::get_alcohol_grams_in_window(&guests_file.consumption, guest_id, consumption_limit.window_minutes, now);
+            if consumed_alcohol_grams + alcohol_grams > consumption_limit.max_alcohol_grams {
+                return Err(ApiError::with_details(ApiErrorCode::ConsumptionLimitReached, json!({
+                    "alcohol_grams": Self::round_grams(consumed_alcohol_grams),
+                    "drink_alcohol_grams": Self::round_grams(alcohol_grams),
+                    "max_alcohol_grams": Self::round_grams(consumption_limit.max_alcohol_grams),
+                    "window_minutes": consumption_limit.window_minutes
+                })));
+            }
+        }
+        let reserved_record = ConsumptionRecord { guest_id, alcohol_grams, poured_at: now, order_id: None };
+        guests_file.consumption.push(reserved_record.clone());
+        Ok(reserved_record)
+    }
+
+    // Takes back orders that left the pump queue without pouring anything. Part of a drink still counts in full since
+    // it may well have been the spirit. The guests and the pump service are never locked together
+    fn settle_orders(&self) {
+        let pump_service = match &self.pump_service {
+            Some(pump_service) => pump_service,
+            None => return
+        };
+        let order_ids: Vec<Uuid> = self.guests_file.lock().unwrap().consumption.iter().filter_map(|record| record.order_id).collect();
+        if order_ids.is_empty() {
+            return;
+        }
+        let mut are_orders_poured = HashMap::new();
+        {
+            let pump_service = pump_service.lock().unwrap();
+            for order_id in order_ids {
+                match pump_service.get_group_record(order_id) {
+                    Some(pump_group_record) if !pump_group_record.is_finished() => {},
+                    Some(pump_group_record) => { are_orders_poured.insert(order_id, !pump_group_record.is_dropped || pump_group_record.pumped_ml > 0.0); },
+                    // Forgotten, e.g. since a restart, so whether it poured isn't known and it counts
+                    None => { are_orders_poured.insert(order_id, true); }
+                }
+            }
+        }
+        if are_orders_poured.is_empty() {
+            return;
+        }
+        let mut guests_file = self.guests_file.lock().unwrap();
+        Self::settle_records(&mut guests_file.consumption, &are_orders_poured);
+        if let Err(error) = self.save(&guests_file) {
+            log::warn!("Couldn't save settled consumption: {:?}", error.details);
+        }
+    }
+
+    fn settle_records(consumption: &mut Vec<ConsumptionRecord>, are_orders_poured: &HashMap<Uuid, bool>) {
+        consumption.retain(|record| record.order_id.and_then(|order_id| are_orders_poured.get(&order_id)) != Some(&false));
+        for record in consumption.iter_mut().filter(|record| record.order_id.is_some_and(|order_id| are_orders_poured.contains_key(&order_id))) {
+            record.order_id = None;
+        }
+    }
+
+    fn check_guest_access(caller: &Caller, profile: &GuestProfile) -> Result<(), ApiError> {
+        if caller.role == Role::Guest && (caller.api_key_id.is_none() || profile.api_key_id != caller.api_key_id) {
+            return Err(ApiError::with_details(ApiErrorCode::InsufficientRole, json!({ "role": Role::Guest, "required_role": Role::Bartender })));
+        }
+        Ok(())
+    }
+
+    fn create_guest_profile(id: Uuid, new_guest_profile: NewGuestProfile) -> Result<GuestProfile, ApiError> {
+        let name = new_guest_profile.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ApiError::new(ApiErrorCode::GuestNameMissing));
+        }
+        let is_body_weight_valid = new_guest_profile.body_weight_kg.is_none_or(|body_weight_kg| body_weight_kg > 0.0);
+        let is_widmark_factor_valid = new_guest_profile.widmark_factor.is_none_or(|widmark_factor| widmark_factor > 0.0 && widmark_factor <= 1.0);
+        if !is_body_weight_valid || !is_widmark_factor_valid {
+            return Err(ApiError::new(ApiErrorCode::GuestProfileInvalid));
+        }
+        Ok(GuestProfile {
+            id,
+            name,
+            api_key_id: new_guest_profile.api_key_id,
+            body_weight_kg: new_guest_profile.body_weight_kg,
+            widmark_factor: new_guest_profile.widmark_factor
+        })
+    }
+
+    fn get_alcohol_grams_in_window(consumption: &[ConsumptionRecord], guest_id: Uuid, window_minutes: u64, now: u64) -> f32 {
+        consumption.iter()
+            .filter(|record| record.guest_id == guest_id && record.poured_at + window_minutes * 60 > now)
+            .map(|record| record.alcohol_grams)
+            .sum()
+    }
+
+    // Widmark formula, letting the body break alcohol down between drinks
+    fn estimate_blood_alcohol_percent(consumption: &[ConsumptionRecord], guest_id: Uuid, body_weight_kg: f32, widmark_factor: f32, now: u64) -> f32 {
+        let mut blood_alcohol_percent: f32 = 0.0;
+        let mut last_poured_at = None;
+        for record in consumption.iter().filter(|record| record.guest_id == guest_id) {
+            if let Some(last_poured_at) = last_poured_at {
+                blood_alcohol_percent = Self::eliminate(blood_alcohol_percent, record.poured_at.saturating_sub(last_poured_at));
+            }
+            blood_alcohol_percent += record.alcohol_grams / (body_weight_kg * 1000.0 * widmark_factor) * 100.0;
+            last_poured_at = Some(record.poured_at);
+        }
+        if let Some(last_poured_at) = last_poured_at {
+            blood_alcohol_percent = Self::eliminate(blood_alcohol_percent, now.saturating_sub(last_poured_at));
+        }
+        (blood_alcohol_percent * 1000.0).round() / 1000.0
+    }
+
+    fn eliminate(blood_alcohol_percent: f32, elapsed_seconds: u64) -> f32 {
+        (blood_alcohol_percent - ELIMINATION_PERCENT_PER_HOUR * elapsed_seconds as f32 / 3600.0).max(0.0)
+    }
+
+    // Rounded as f64 so the message doesn't show float noise
+    fn round_grams(grams: f32) -> f64 {
+        (grams as f64 * 10.0).round() / 10.0
+    }
+
+    fn get_now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+    }
+
+    fn create_guest_not_found_error(id: Uuid) -> ApiError {
+        ApiError::with_details(ApiErrorCode::GuestNotFound, json!({ "guest_id": id }))
+    }
+
+    fn save(&self, guests_file: &GuestsFile) -> Result<(), ApiError> {
+        let guests_json = serde_json::to_string_pretty(guests_file).map_err(Self::create_save_error)?;
+        if let Some(guests_directory) = self.guests_file_path.parent() {
+            fs::create_dir_all(guests_directory).map_err(Self::create_save_error)?;
+        }
+        fs::write(&self.guests_file_path, guests_json).map_err(Self::create_save_error)
+    }
+
+    fn create_save_error<E: ToString>(error: E) -> ApiError {
+        ApiError::with_details(ApiErrorCode::GuestsNotSaved, json!({ "error": error.to_string() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::env;
+    use uuid::Uuid;
+    use crate::api::models::{ ApiError, ApiErrorCode, ConsumptionLimit, ConsumptionRecord, GuestsFile };
+    use super::GuestService;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn record(guest_id: Uuid, alcohol_grams: f32, seconds_ago: u64) -> ConsumptionRecord {
+        ConsumptionRecord { guest_id, alcohol_grams, poured_at: NOW - seconds_ago, order_id: None }
+    }
+
+    #[test]
+    fn sums_alcohol_within_window_for_guest_only() {
+        let guest_id = Uuid::new_v4();
+        let other_guest_id = Uuid::new_v4();
+        let consumption = vec![
+            record(guest_id, 10.0, 2 * 60 * 60),
+            record(guest_id, 14.0, 30 * 60),
+            record(other_guest_id, 20.0, 10 * 60),
+            record(guest_id, 7.0, 0)
+        ];
+        assert_eq!(GuestService::get_alcohol_grams_in_window(&consumption, guest_id, 60, NOW), 21.0);
+        assert_eq!(GuestService::get_alcohol_grams_in_window(&consumption, guest_id, 180, NOW), 31.0);
+        assert_eq!(GuestService::get_alcohol_grams_in_window(&consumption, Uuid::new_v4(), 180, NOW), 0.0);
+    }
+
+    #[test]
+    fn drops_record_exactly_at_window_edge() {
+        let guest_id = Uuid::new_v4();
+        let consumption = vec![record(guest_id, 10.0, 60 * 60)];
+        assert_eq!(GuestService::get_alcohol_grams_in_window(&consumption, guest_id, 60, NOW), 0.0);
+    }
+
+    #[test]
+    fn estimates_blood_alcohol_with_widmark_formula() {
+        let guest_id = Uuid::new_v4();
+        // 14 g into 70 kg * 0.7 is 0.02857 %
+        let consumption = vec![record(guest_id, 14.0, 0)];
+        assert_eq!(GuestService::estimate_blood_alcohol_percent(&consumption, guest_id, 70.0, 0.7, NOW), 0.029);
+    }
+
+    #[test]
+    fn eliminates_alcohol_between_and_after_drinks() {
+        let guest_id = Uuid::new_v4();
+        // 49 g into 70 kg * 0.7 is 0.1 %, one hour later 0.015 % is gone before and after the second drink
+        let consumption = vec![record(guest_id, 49.0, 2 * 60 * 60), record(guest_id, 49.0, 60 * 60)];
+        assert_eq!(GuestService::estimate_blood_alcohol_percent(&consumption, guest_id, 70.0, 0.7, NOW), 0.17);
+    }
+
+    #[test]
+    fn never_estimates_below_zero() {
+        let guest_id = Uuid::new_v4();
+        let consumption = vec![record(guest_id, 14.0, 24 * 60 * 60)];
+        assert_eq!(GuestService::estimate_blood_alcohol_percent(&consumption, guest_id, 70.0, 0.7, NOW), 0.0);
+        assert_eq!(GuestService::estimate_blood_alcohol_percent(&[], guest_id, 70.0, 0.7, NOW), 0.0);
+    }
+
+    fn create_guest_service(max_alcohol_grams: f32) -> GuestService {
+        let guests_file_path = env::temp_dir().join(format!("drink-o-matic-guests-{}.json", Uuid::new_v4()));
+        let consumption_limits = vec![ConsumptionLimit { window_minutes: 60, max_alcohol_grams }];
+        GuestService::new(GuestsFile { guests: vec![], consumption: vec![] }, guests_file_path, consumption_limits, None)
+    }
+
+    #[test]
+    fn records_pour_within_limits() {
+        let guest_service = create_guest_service(20.0);
+        let guest_id = Uuid::new_v4();
+        let order_id = Uuid::new_v4();
+        assert!(guest_service.pour_within_limits(Some(guest_id), order_id, 14.0, || Ok(())).is_ok());
+        assert_eq!(guest_service.guests_file.lock().unwrap().consumption[0].order_id, Some(order_id));
+        let error = guest_service.pour_within_limits(Some(guest_id), Uuid::new_v4(), 14.0, || Ok(())).unwrap_err();
+        assert_eq!(error.code, ApiErrorCode::ConsumptionLimitReached);
+        assert_eq!(guest_service.guests_file.lock().unwrap().consumption.len(), 1);
+        std::fs::remove_file(&guest_service.guests_file_path).ok();
+    }
+
+    #[test]
+    fn rolls_back_reservation_when_pour_fails() {
+        let guest_service = create_guest_service(20.0);
+        let guest_id = Uuid::new_v4();
+        let result: Result<(), ApiError> = guest_service.pour_within_limits(Some(guest_id), Uuid::new_v4(), 14.0, || {
+            // The guests aren't locked while pouring
+            assert_eq!(guest_service.get_guests().len(), 0);
+            Err(ApiError::new(ApiErrorCode::PumpQueueNotProcessing))
+        });
+        assert!(result.is_err());
+        assert!(guest_service.guests_file.lock().unwrap().consumption.is_empty());
+        assert!(guest_service.pour_within_limits(Some(guest_id), Uuid::new_v4(), 14.0, || Ok(())).is_ok());
+        std::fs::remove_file(&guest_service.guests_file_path).ok();
+    }
+
+    #[test]
+    fn takes_back_orders_that_poured_nothing() {
+        let guest_id = Uuid::new_v4();
+        let (poured_order_id, cleared_order_id, queued_order_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let order_record = |order_id| ConsumptionRecord { order_id: Some(order_id), ..record(guest_id, 14.0, 0) };
+        let mut consumption = vec![
+            record(guest_id, 10.0, 60),
+            order_record(poured_order_id),
+            order_record(cleared_order_id),
+            order_record(queued_order_id)
+        ];
+        let are_orders_poured = HashMap::from([(poured_order_id, true), (cleared_order_id, false)]);
+        GuestService::settle_records(&mut consumption, &are_orders_poured);
+        assert_eq!(consumption, vec![record(guest_id, 10.0, 60), record(guest_id, 14.0, 0), order_record(queued_order_id)]);
+    }
+}
@@ -0,0 +1,21 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::{ Arc, Mutex };
+use crate::api::models::{ Config, GuestsFile };
+use crate::api::{ GuestService, PumpService };
+
+pub struct GuestServiceFactory {}
+
+impl GuestServiceFactory {
+    pub fn create(config: &Config, pump_service: Option<Arc<Mutex<PumpService>>>) -> Result<GuestService, String> {
+        let file_path = config.guests_file_path.clone();
+        let guests_file: GuestsFile = match fs::read_to_string(&file_path) {
+            Ok(guests_json) => serde_json::from_str(&guests_json)
+                .map_err(|error| format!("Couldn't parse {}: {}", file_path.display(), error))?,
+            // No guests have been added yet
+            Err(error) if error.kind() == ErrorKind::NotFound => GuestsFile::default(),
+            Err(error) => return Err(format!("Couldn't read {}: {}", file_path.display(), error))
+        };
+        Ok(GuestService::new(guests_file, file_path, config.consumption_limits.clone(), pump_service))
+    }
+}
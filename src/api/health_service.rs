@@ -0,0 +1,61 @@
+use std::sync::{ Arc, Mutex };
+use crate::api::models::{ DiagnosticsReport, DiagnosticStatus };
+use crate::api::PumpService;
+
+pub struct HealthService {
+    pump_service: Option<Arc<Mutex<PumpService>>>,
+    gpio_lines_status: Option<DiagnosticStatus>,
+    settings_status: Option<DiagnosticStatus>
+}
+
+impl HealthService {
+    pub fn new(pump_service: Option<Arc<Mutex<PumpService>>>, gpio_lines_status: Option<DiagnosticStatus>, settings_status: Option<DiagnosticStatus>) -> HealthService {
+        HealthService { pump_service, gpio_lines_status, settings_status }
+    }
+
+    pub fn get_readiness_report(&self) -> DiagnosticsReport {
+        let mut readiness_report = DiagnosticsReport::default();
+        match &self.pump_service {
+            Some(pump_service) => match pump_service.lock() {
+                Ok(pump_service) => {
+                    if pump_service.is_daemon_alive() {
+                        readiness_report.add("daemon_thread", DiagnosticStatus::Pass, "Pump queue daemon is running".to_string());
+                    }
+                    else {
+                        readiness_report.add("daemon_thread", DiagnosticStatus::Fail, "Pump queue daemon isn't running".to_string());
+                    }
+                    // Pouring still works, the lines just want rinsing
+                    let cleaning_due_pump_numbers = pump_service.get_cleaning_due_pump_numbers();
+                    if cleaning_due_pump_numbers.is_empty() {
+                        readiness_report.add("cleaning", DiagnosticStatus::Pass, "No pump is due for cleaning".to_string());
+                    }
+                    else {
+                        readiness_report.add("cleaning", DiagnosticStatus::Warn, format!("Pumps {:?} are due for cleaning", cleaning_due_pump_numbers));
+                    }
+                    let maintenance_reminders = pump_service.get_maintenance_reminders(&[]);
+                    if maintenance_reminders.is_empty() {
+                        readiness_report.add("maintenance", DiagnosticStatus::Pass, "No maintenance is due".to_string());
+                    }
+                    else {
+                        let messages: Vec<String> = maintenance_reminders.into_iter().map(|maintenance_reminder| maintenance_reminder.message).collect();
+                        readiness_report.add("maintenance", DiagnosticStatus::Warn, messages.join("; "));
+                    }
+                },
+                Err(_) => readiness_report.add("pump_service", DiagnosticStatus::Fail, "Pump service is poisoned".to_string())
+            },
+            None => readiness_report.add("pump_service", DiagnosticStatus::Fail, "Pump service didn't start".to_string())
+        }
+        // Lines are only requested once, on startup
+        match self.gpio_lines_status {
+            Some(DiagnosticStatus::Pass) => readiness_report.add("gpio_lines", DiagnosticStatus::Pass, "All pump lines are acquired".to_string()),
+            _ => readiness_report.add("gpio_lines", DiagnosticStatus::Fail, "Pump lines weren't acquired on startup".to_string())
+        }
+        // Settings only exist with the "bff" feature
+        match self.settings_status {
+            Some(DiagnosticStatus::Fail) => readiness_report.add("settings", DiagnosticStatus::Fail, "Settings didn't load on startup".to_string()),
+            Some(_) => readiness_report.add("settings", DiagnosticStatus::Pass, "Settings are loaded".to_string()),
+            None => {}
+        }
+        readiness_report
+    }
+}
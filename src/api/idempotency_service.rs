@@ -0,0 +1,69 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{ Duration, Instant };
+use rocket::Request;
+use rocket::request::{ FromRequest, Outcome };
+use crate::api::models::ApiError;
+use crate::api::{ get_api_key, ApiKeyService };
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+struct RememberedResponse {
+    remembered_at: Instant,
+    response: Box<dyn Any + Send>
+}
+
+// Remembers the responses of requests sent with an Idempotency-Key header so retrying them doesn't pour twice
+pub struct IdempotencyService {
+    window: Duration,
+    remembered_responses: Mutex<HashMap<String, RememberedResponse>>
+}
+
+impl IdempotencyService {
+    pub fn new(window: Duration) -> IdempotencyService {
+        IdempotencyService { window, remembered_responses: Mutex::new(HashMap::new()) }
+    }
+
+    // Keyed requests run one at a time so a retry can't slip in while the original is still being queued.
+    // Failures aren't remembered, so a request that was refused can be retried with the same key
+    pub fn run_once<T: Clone + Send + 'static>(&self, key: &str, run: impl FnOnce() -> Result<T, ApiError>) -> Result<T, ApiError> {
+        let mut remembered_responses = self.remembered_responses.lock().unwrap();
+        let now = Instant::now();
+        remembered_responses.retain(|_, remembered_response| now.duration_since(remembered_response.remembered_at) < self.window);
+        if let Some(response) = remembered_responses.get(key).and_then(|remembered_response| remembered_response.response.downcast_ref::<T>()) {
+            return Ok(response.clone());
+        }
+        let response = run()?;
+        remembered_responses.insert(key.to_string(), RememberedResponse { remembered_at: now, response: Box::new(response.clone()) });
+        Ok(response)
+    }
+}
+
+pub struct Idempotency<'r> {
+    idempotency_service: Option<&'r IdempotencyService>,
+    key: Option<String>
+}
+
+impl Idempotency<'_> {
+    pub fn run_once<T: Clone + Send + 'static>(&self, run: impl FnOnce() -> Result<T, ApiError>) -> Result<T, ApiError> {
+        match (self.idempotency_service, &self.key) {
+            (Some(idempotency_service), Some(key)) => idempotency_service.run_once(key, run),
+            _ => run()
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Idempotency<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // A key only has to be unique for the client sending it and the endpoint it's sent to
+        let key = request.headers().get_one(IDEMPOTENCY_KEY_HEADER)
+            .map(|key| key.trim())
+            .filter(|key| !key.is_empty())
+            .map(|key| format!("{} {} {} {}", ApiKeyService::hash_key(get_api_key(request).unwrap_or_default()), request.method(), request.uri(), key));
+        Outcome::Success(Idempotency { idempotency_service: request.rocket().state::<IdempotencyService>(), key })
+    }
+}
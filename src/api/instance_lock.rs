@@ -0,0 +1,35 @@
+use std::fs::{ self, File, OpenOptions, TryLockError };
+use std::io::Write;
+use std::path::PathBuf;
+use std::process;
+
+// Holds an advisory lock on the lock file for as long as it's alive so a second instance can't fight over the GPIO lines.
+// The kernel drops the lock with the process, so a crash never leaves a stale one behind
+pub struct InstanceLock {
+    _lock_file: File
+}
+
+impl InstanceLock {
+    pub fn acquire(file_path: PathBuf) -> Result<InstanceLock, String> {
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).map_err(|error| format!("Couldn't create lock directory: {}", error))?;
+        }
+        let mut lock_file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&file_path)
+            .map_err(|error| format!("Couldn't open lock {}: {}", file_path.display(), error))?;
+        match lock_file.try_lock() {
+            Ok(()) => {},
+            Err(TryLockError::WouldBlock) => {
+                // The pid is only there to make the error helpful
+                return match fs::read_to_string(&file_path).ok().and_then(|pid| pid.trim().parse::<u32>().ok()) {
+                    Some(pid) => Err(format!("Another instance (pid {}) holds {}", pid, file_path.display())),
+                    None => Err(format!("Another instance holds {}", file_path.display()))
+                };
+            },
+            Err(TryLockError::Error(error)) => return Err(format!("Couldn't lock {}: {}", file_path.display(), error))
+        }
+        lock_file.set_len(0)
+            .and_then(|_| lock_file.write_all(process::id().to_string().as_bytes()))
+            .map_err(|error| format!("Couldn't write pid to {}: {}", file_path.display(), error))?;
+        Ok(InstanceLock { _lock_file: lock_file })
+    }
+}
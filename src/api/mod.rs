@@ -1,20 +1,72 @@
+mod pump_driver;
 mod pump_service;
 mod pump_service_factory;
 #[cfg(feature = "bff")]
 mod settings_service;
 #[cfg(feature = "bff")]
 mod settings_service_factory;
+#[cfg(feature = "bff")]
+mod guest_service;
+#[cfg(feature = "bff")]
+mod guest_service_factory;
+#[cfg(feature = "bff")]
+mod drink_service;
+#[cfg(feature = "bff")]
+mod order_service;
 mod resource_service;
 mod resource_service_factory;
+mod resource_catalog;
+mod xml_resource_catalog;
+mod fluent_resource_catalog;
+mod fluent_converter;
+mod config_factory;
+mod instance_lock;
+mod health_service;
+mod api_key_service;
+mod api_key_service_factory;
+mod access_guards;
+mod cors_fairing;
+mod requested_cup;
+mod idempotency_service;
+mod schedule_service;
+mod schedule_service_factory;
+mod cleaning_service;
+mod cleaning_service_factory;
 pub mod models;
 #[cfg(not(feature = "use-gpio"))]
 pub mod mock;
 
+pub use pump_driver::*;
 pub use pump_service::*;
 pub use pump_service_factory::*;
 #[cfg(feature = "bff")]
 pub use settings_service::*;
 #[cfg(feature = "bff")]
 pub use settings_service_factory::*;
+#[cfg(feature = "bff")]
+pub use guest_service::*;
+#[cfg(feature = "bff")]
+pub use guest_service_factory::*;
+#[cfg(feature = "bff")]
+pub use drink_service::*;
+#[cfg(feature = "bff")]
+pub use order_service::*;
 pub use resource_service::*;
 pub use resource_service_factory::*;
+pub use resource_catalog::*;
+pub use xml_resource_catalog::*;
+pub use fluent_resource_catalog::*;
+pub use fluent_converter::*;
+pub use config_factory::*;
+pub use instance_lock::*;
+pub use health_service::*;
+pub use api_key_service::*;
+pub use api_key_service_factory::*;
+pub use access_guards::*;
+pub use cors_fairing::*;
+pub use requested_cup::*;
+pub use idempotency_service::*;
+pub use schedule_service::*;
+pub use schedule_service_factory::*;
+pub use cleaning_service::*;
+pub use cleaning_service_factory::*;
@@ -0,0 +1,61 @@
+use std::convert::Infallible;
+use rocket::Request;
+use rocket::request::{ FromRequest, Outcome };
+
+const LANG_QUERY_PARAMETER: &str = "lang";
+const ACCEPT_LANGUAGE_HEADER: &str = "Accept-Language";
+
+// Locales a client asked for, most preferred first
+#[derive(Clone, Default)]
+pub struct AcceptedLocales {
+    pub locales: Vec<String>
+}
+
+impl AcceptedLocales {
+    pub fn from_request(request: &Request<'_>) -> AcceptedLocales {
+        let mut locales = vec![];
+        // An explicit "?lang=" beats whatever the browser sends
+        if let Some(Ok(lang)) = request.query_value::<&str>(LANG_QUERY_PARAMETER) {
+            locales.push(Self::normalize(lang));
+        }
+        if let Some(accept_language) = request.headers().get_one(ACCEPT_LANGUAGE_HEADER) {
+            locales.append(&mut Self::parse_accept_language(accept_language));
+        }
+        locales.retain(|locale| !locale.is_empty());
+        AcceptedLocales { locales }
+    }
+
+    pub fn parse_accept_language(accept_language: &str) -> Vec<String> {
+        let mut weighted_locales: Vec<(String, f32)> = accept_language.split(',')
+            .filter_map(|language_range| {
+                let mut parts = language_range.split(';');
+                let locale = Self::normalize(parts.next()?);
+                let weight = parts
+                    .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                    .find_map(|weight| weight.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                if locale == "*" || weight <= 0.0 {
+                    return None;
+                }
+                Some((locale, weight))
+            })
+            .collect();
+        // Stable so equally weighted locales keep the client's order
+        weighted_locales.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        weighted_locales.into_iter().map(|(locale, _)| locale).collect()
+    }
+
+    pub fn normalize(locale: &str) -> String {
+        locale.trim().replace('_', "-").to_lowercase()
+    }
+}
+
+// For responses that carry messages of their own, not just errors
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptedLocales {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(AcceptedLocales::from_request(request))
+    }
+}
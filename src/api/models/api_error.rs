@@ -0,0 +1,218 @@
+use std::sync::Arc;
+use rocket::Request;
+use rocket::http::Status;
+use rocket::response::{ self, status, Responder };
+use rocket::serde::json::Json;
+use serde::{ Deserialize, Serialize };
+use serde_json::{ json, Value };
+use crate::api::models::AcceptedLocales;
+use crate::api::ResourceService;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+// Settings, order and guest codes are only raised with the "bff" feature but stay listed so every code is defined in one place
+#[cfg_attr(not(feature = "bff"), allow(dead_code))]
+pub enum ApiErrorCode {
+    RouteNotFound,
+    MalformedRequest,
+    InternalError,
+    PumpNotFound,
+    MlToPumpMissing,
+    MlToPumpNotANumber,
+    MlToPumpNotPositive,
+    PumpJobsMissing,
+    PumpJobNotFound,
+    PumpJobRunning,
+    PumpGroupCupMismatch,
+    PriorityInvalid,
+    PourAmountInvalid,
+    UnitOfMeasureUnknown,
+    PumpQueueNotProcessing,
+    PumpQueueNotEmpty,
+    PumpsBeingCleaned,
+    MaintenanceTaskNotFound,
+    AuthenticationRequired,
+    InsufficientRole,
+    ApiKeyNotFound,
+    ApiKeyNameMissing,
+    ApiKeysNotSaved,
+    SettingsInvalid,
+    DrinkNotFound,
+    CupNotFound,
+    CupNotSelected,
+    DrinkHasNoIngredients,
+    PourModifiersInvalid,
+    CupWouldOverflow,
+    IngredientNotOnPump,
+    GuestNotFound,
+    GuestNameMissing,
+    GuestProfileInvalid,
+    ConsumptionLimitReached,
+    PendingOrderLimitReached,
+    GuestsNotSaved,
+    ScheduleNotFound,
+    ScheduleInvalid,
+    SchedulesNotSaved,
+    CleaningProgramNotFound,
+    CleaningRunNotFound,
+    CleaningRunActive,
+    CleaningRunNotAwaitingConfirmation,
+    SettingsSerializationFailed,
+    SettingsDirectoryNotCreated,
+    SettingsFileNotOpened,
+    SettingsFileNotTruncated,
+    SettingsFileNotWritten
+}
+
+impl ApiErrorCode {
+    pub fn get_status(&self) -> Status {
+        match self {
+            ApiErrorCode::RouteNotFound
+            | ApiErrorCode::PumpNotFound
+            | ApiErrorCode::PumpJobNotFound
+            | ApiErrorCode::MaintenanceTaskNotFound
+            | ApiErrorCode::ApiKeyNotFound
+            | ApiErrorCode::DrinkNotFound
+            | ApiErrorCode::CupNotFound
+            | ApiErrorCode::GuestNotFound
+            | ApiErrorCode::ScheduleNotFound
+            | ApiErrorCode::CleaningProgramNotFound
+            | ApiErrorCode::CleaningRunNotFound => Status::NotFound,
+            ApiErrorCode::MalformedRequest
+            | ApiErrorCode::MlToPumpMissing
+            | ApiErrorCode::MlToPumpNotANumber
+            | ApiErrorCode::MlToPumpNotPositive
+            | ApiErrorCode::PumpJobsMissing
+            | ApiErrorCode::PriorityInvalid
+            | ApiErrorCode::PourAmountInvalid
+            | ApiErrorCode::UnitOfMeasureUnknown
+            | ApiErrorCode::ApiKeyNameMissing
+            | ApiErrorCode::SettingsInvalid
+            | ApiErrorCode::CupNotSelected
+            | ApiErrorCode::DrinkHasNoIngredients
+            | ApiErrorCode::PourModifiersInvalid
+            | ApiErrorCode::CupWouldOverflow
+            | ApiErrorCode::GuestNameMissing
+            | ApiErrorCode::GuestProfileInvalid
+            | ApiErrorCode::ScheduleInvalid => Status::UnprocessableEntity,
+            ApiErrorCode::IngredientNotOnPump
+            | ApiErrorCode::PumpJobRunning
+            | ApiErrorCode::PumpGroupCupMismatch
+            | ApiErrorCode::PumpQueueNotEmpty
+            | ApiErrorCode::CleaningRunActive
+            | ApiErrorCode::CleaningRunNotAwaitingConfirmation => Status::Conflict,
+            ApiErrorCode::ConsumptionLimitReached
+            | ApiErrorCode::PendingOrderLimitReached => Status::TooManyRequests,
+            ApiErrorCode::PumpQueueNotProcessing
+            | ApiErrorCode::PumpsBeingCleaned => Status::ServiceUnavailable,
+            ApiErrorCode::AuthenticationRequired => Status::Unauthorized,
+            ApiErrorCode::InsufficientRole => Status::Forbidden,
+            ApiErrorCode::InternalError
+            | ApiErrorCode::ApiKeysNotSaved
+            | ApiErrorCode::GuestsNotSaved
+            | ApiErrorCode::SchedulesNotSaved
+            | ApiErrorCode::SettingsSerializationFailed
+            | ApiErrorCode::SettingsDirectoryNotCreated
+            | ApiErrorCode::SettingsFileNotOpened
+            | ApiErrorCode::SettingsFileNotTruncated
+            | ApiErrorCode::SettingsFileNotWritten => Status::InternalServerError
+        }
+    }
+
+    pub fn get_resource_name(&self) -> &'static str {
+        match self {
+            ApiErrorCode::RouteNotFound => "route_not_found_error_message",
+            ApiErrorCode::MalformedRequest => "malformed_request_error_message",
+            ApiErrorCode::InternalError => "internal_error_message",
+            ApiErrorCode::PumpNotFound => "invalid_pump_number_error_message",
+            ApiErrorCode::MlToPumpMissing => "expected_ml_to_pump_error_message",
+            ApiErrorCode::MlToPumpNotANumber => "ml_to_pump_parse_error_message",
+            ApiErrorCode::MlToPumpNotPositive => "invalid_ml_to_pump_error_message",
+            ApiErrorCode::PumpJobsMissing => "pump_jobs_missing_error_message",
+            ApiErrorCode::PumpJobNotFound => "pump_job_not_found_error_message_template",
+            ApiErrorCode::PumpJobRunning => "pump_job_running_error_message_template",
+            ApiErrorCode::PumpGroupCupMismatch => "pump_group_cup_mismatch_error_message_template",
+            ApiErrorCode::PriorityInvalid => "priority_invalid_error_message_template",
+            ApiErrorCode::PourAmountInvalid => "pour_amount_invalid_error_message",
+            ApiErrorCode::UnitOfMeasureUnknown => "unit_of_measure_unknown_error_message_template",
+            ApiErrorCode::PumpQueueNotProcessing => "daemon_not_running_error_message",
+            ApiErrorCode::PumpQueueNotEmpty => "pump_queue_not_empty_error_message_template",
+            ApiErrorCode::PumpsBeingCleaned => "pumps_being_cleaned_error_message",
+            ApiErrorCode::MaintenanceTaskNotFound => "maintenance_task_not_found_error_message_template",
+            ApiErrorCode::AuthenticationRequired => "authentication_required_error_message",
+            ApiErrorCode::InsufficientRole => "insufficient_role_error_message_template",
+            ApiErrorCode::ApiKeyNotFound => "api_key_not_found_error_message_template",
+            ApiErrorCode::ApiKeyNameMissing => "api_key_name_missing_error_message",
+            ApiErrorCode::ApiKeysNotSaved => "api_keys_not_saved_error_message_template",
+            ApiErrorCode::SettingsInvalid => "invalid_settings_error_message",
+            ApiErrorCode::DrinkNotFound => "drink_not_found_error_message_template",
+            ApiErrorCode::CupNotFound => "cup_not_found_error_message_template",
+            ApiErrorCode::CupNotSelected => "cup_not_selected_error_message",
+            ApiErrorCode::DrinkHasNoIngredients => "drink_has_no_ingredients_error_message",
+            ApiErrorCode::PourModifiersInvalid => "pour_modifiers_invalid_error_message_template",
+            ApiErrorCode::CupWouldOverflow => "cup_would_overflow_error_message_template",
+            ApiErrorCode::IngredientNotOnPump => "ingredient_not_on_pump_error_message_template",
+            ApiErrorCode::GuestNotFound => "guest_not_found_error_message_template",
+            ApiErrorCode::GuestNameMissing => "guest_name_missing_error_message",
+            ApiErrorCode::GuestProfileInvalid => "guest_profile_invalid_error_message",
+            ApiErrorCode::ConsumptionLimitReached => "consumption_limit_reached_error_message_template",
+            ApiErrorCode::PendingOrderLimitReached => "pending_order_limit_reached_error_message_template",
+            ApiErrorCode::GuestsNotSaved => "guests_not_saved_error_message_template",
+            ApiErrorCode::ScheduleNotFound => "schedule_not_found_error_message_template",
+            ApiErrorCode::ScheduleInvalid => "schedule_invalid_error_message_template",
+            ApiErrorCode::SchedulesNotSaved => "schedules_not_saved_error_message_template",
+            ApiErrorCode::CleaningProgramNotFound => "cleaning_program_not_found_error_message_template",
+            ApiErrorCode::CleaningRunNotFound => "cleaning_run_not_found_error_message",
+            ApiErrorCode::CleaningRunActive => "cleaning_run_active_error_message_template",
+            ApiErrorCode::CleaningRunNotAwaitingConfirmation => "cleaning_run_not_awaiting_confirmation_error_message",
+            ApiErrorCode::SettingsSerializationFailed => "settings_serialization_error_message_template",
+            ApiErrorCode::SettingsDirectoryNotCreated => "create_settings_directory_error_message_template",
+            ApiErrorCode::SettingsFileNotOpened => "create_or_open_settings_file_error_message_template",
+            ApiErrorCode::SettingsFileNotTruncated => "truncating_settings_file_error_message",
+            ApiErrorCode::SettingsFileNotWritten => "write_to_settings_file_error_message_template"
+        }
+    }
+}
+
+// The message is rendered when responding so it can come from the resource service the API is running with
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    pub code: ApiErrorCode,
+    pub details: Option<Value>
+}
+
+impl ApiError {
+    pub fn new(code: ApiErrorCode) -> ApiError {
+        ApiError { code, details: None }
+    }
+
+    pub fn with_details(code: ApiErrorCode, details: Value) -> ApiError {
+        ApiError { code, details: Some(details) }
+    }
+
+    pub fn render_message(&self, resource_service: &ResourceService, accepted_locales: &AcceptedLocales) -> String {
+        let resource_name = self.code.get_resource_name();
+        let message_data = self.details.clone().unwrap_or_else(|| json!({}));
+        resource_service.render_localized_resource_template_string_by_name(&accepted_locales.locales, resource_name, &message_data)
+    }
+}
+
+#[derive(Serialize)]
+struct ApiErrorBody {
+    code: ApiErrorCode,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Value>
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let message = match request.rocket().state::<Arc<ResourceService>>() {
+            Some(resource_service) => self.render_message(resource_service, &AcceptedLocales::from_request(request)),
+            None => self.code.get_resource_name().to_string()
+        };
+        let status = self.code.get_status();
+        let body = ApiErrorBody { code: self.code, message, details: self.details };
+        status::Custom(status, Json(body)).respond_to(request)
+    }
+}
@@ -0,0 +1,35 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use crate::api::models::Role;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub role: Role,
+    // Only the SHA-256 of the key is stored; the key itself is shown once when it's created
+    #[serde(rename = "keyHash")]
+    pub key_hash: String
+}
+
+// What GET /api_keys shows of a stored key; the hash stays in the keys file
+#[derive(Serialize)]
+pub struct ListedApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub role: Role
+}
+
+#[derive(Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub role: Role
+}
+
+#[derive(Serialize)]
+pub struct CreatedApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub role: Role,
+    pub key: String
+}
@@ -0,0 +1,12 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use crate::api::models::Role;
+
+// Who made a request; anonymous when authentication is disabled or the key management endpoint is used locally
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Caller {
+    pub role: Role,
+    #[serde(rename = "apiKeyId")]
+    pub api_key_id: Option<Uuid>,
+    pub name: Option<String>
+}
@@ -0,0 +1,28 @@
+use serde::{ Deserialize, Serialize };
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CleaningStep {
+    // Runs the pumps one after another, every pump if none are listed, for either ml or a multiple of each line's dead volume
+    Pump {
+        #[serde(default, rename = "pumpNumbers")]
+        pump_numbers: Vec<u8>,
+        ml: Option<f32>,
+        #[serde(rename = "deadVolumes")]
+        dead_volumes: Option<f32>
+    },
+    Wait {
+        seconds: u64
+    },
+    // Holds the program until someone confirms they did what the message asks, e.g. moving the intakes to water
+    Prompt {
+        message: String
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CleaningProgram {
+    pub id: String,
+    pub name: String,
+    pub steps: Vec<CleaningStep>
+}
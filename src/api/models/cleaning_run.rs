@@ -0,0 +1,55 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use crate::api::models::{ ApiErrorCode, CleaningStep };
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CleaningRunStatus {
+    Pumping,
+    Waiting,
+    AwaitingConfirmation,
+    Completed,
+    Cancelled,
+    Failed
+}
+
+impl CleaningRunStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, CleaningRunStatus::Completed | CleaningRunStatus::Cancelled | CleaningRunStatus::Failed)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewCleaningRun {
+    #[serde(rename = "programId", alias = "program_id")]
+    pub program_id: String
+}
+
+// The run in progress, or the last one once it's finished. Times are unix milliseconds
+#[derive(Serialize, Clone)]
+pub struct CleaningRun {
+    pub id: Uuid,
+    #[serde(rename = "programId")]
+    pub program_id: String,
+    #[serde(rename = "programName")]
+    pub program_name: String,
+    pub status: CleaningRunStatus,
+    // Counting from 0
+    #[serde(rename = "stepIndex")]
+    pub step_index: usize,
+    #[serde(rename = "stepCount")]
+    pub step_count: usize,
+    pub step: Option<CleaningStep>,
+    // When the pumping or waiting of the step should be done
+    #[serde(rename = "stepEndsAt")]
+    pub step_ends_at: Option<u64>,
+    #[serde(rename = "startedAt")]
+    pub started_at: u64,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<u64>,
+    #[serde(rename = "errorCode")]
+    pub error_code: Option<ApiErrorCode>,
+    #[serde(rename = "errorDetails")]
+    pub error_details: Option<Value>
+}
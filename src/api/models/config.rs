@@ -0,0 +1,71 @@
+use std::fmt;
+use std::path::PathBuf;
+use serde::Serialize;
+use crate::api::models::{ MaintenanceThreshold, PumpLine, UnitOfMeasure };
+#[cfg(feature = "bff")]
+use crate::api::models::{ ConsumptionLimit, CupOverflowPolicy };
+
+#[derive(Clone)]
+pub struct Config {
+    pub rpi_chip_name: String,
+    pub ordered_pump_pin_numbers: Vec<u32>,
+    // The H-bridge direction line of each pump, if it can run backwards
+    pub direction_pin_numbers: Vec<Option<u32>>,
+    pub milliseconds_per_ml: u64,
+    pub is_relay_inverted: bool,
+    pub pump_lines: Vec<PumpLine>,
+    pub pumps_file_path: PathBuf,
+    #[cfg(feature = "bff")]
+    pub settings_file_path: PathBuf,
+    #[cfg(feature = "bff")]
+    pub guests_file_path: PathBuf,
+    #[cfg(feature = "bff")]
+    pub consumption_limits: Vec<ConsumptionLimit>,
+    #[cfg(feature = "bff")]
+    pub standard_drink_grams: f32,
+    #[cfg(feature = "bff")]
+    pub shot_ml: f32,
+    pub units_of_measure: Vec<UnitOfMeasure>,
+    #[cfg(feature = "bff")]
+    pub default_cup_fill_ratio: f32,
+    #[cfg(feature = "bff")]
+    pub cup_overflow_policy: CupOverflowPolicy,
+    pub strings_xml_file_path: PathBuf,
+    pub default_locale: String,
+    pub log_locale: String,
+    pub lock_file_path: PathBuf,
+    pub is_authentication_enabled: bool,
+    pub api_keys_file_path: PathBuf,
+    pub cors_allowed_origins: Vec<String>,
+    pub cors_allowed_methods: Vec<String>,
+    pub cors_allowed_headers: Vec<String>,
+    pub idempotency_window_seconds: u64,
+    pub is_fair_queueing_enabled: bool,
+    pub max_pending_orders_per_requester: Option<u32>,
+    pub schedules_file_path: PathBuf,
+    pub cleaning_programs_file_path: PathBuf,
+    pub cleaning_due_after_idle_hours: Option<f32>,
+    pub cleaning_due_after_ml: Option<f32>,
+    pub replace_tubing_threshold: MaintenanceThreshold,
+    pub replace_pump_threshold: MaintenanceThreshold,
+    pub tls_certificate_file_path: Option<PathBuf>,
+    pub tls_key_file_path: Option<PathBuf>
+}
+
+impl Config {
+    pub fn get_number_of_pumps(&self) -> u8 {
+        self.ordered_pump_pin_numbers.len() as u8
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ConfigError {
+    pub key: String,
+    pub message: String
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.key, self.key.to_uppercase(), self.message)
+    }
+}
@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy)]
+pub struct ConsumptionLimit {
+    #[serde(rename = "windowMinutes")]
+    pub window_minutes: u64,
+    #[serde(rename = "maxAlcoholGrams")]
+    pub max_alcohol_grams: f32
+}
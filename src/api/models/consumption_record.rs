@@ -0,0 +1,24 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use crate::api::models::GuestProfile;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ConsumptionRecord {
+    // A guest profile id or, for guest keys without a profile, the API key id
+    #[serde(rename = "guestId")]
+    pub guest_id: Uuid,
+    #[serde(rename = "alcoholGrams")]
+    pub alcohol_grams: f32,
+    // Seconds since the unix epoch
+    #[serde(rename = "pouredAt")]
+    pub poured_at: u64,
+    // Set while the order is in the pump queue, so a drink that's stopped or cleared before anything was poured doesn't count
+    #[serde(rename = "orderId", default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<Uuid>
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct GuestsFile {
+    pub guests: Vec<GuestProfile>,
+    pub consumption: Vec<ConsumptionRecord>
+}
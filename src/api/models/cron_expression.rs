@@ -0,0 +1,206 @@
+use chrono::{ DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Timelike, Utc };
+
+// Long enough to reach a 29th of February that has to fall on a particular weekday
+const MAX_SEARCH_DAYS: i64 = 8 * 366;
+
+// A crontab style recurrence: minute, hour, day of month, month and day of week (0 or 7 is Sunday). Each field is "*",
+// a number, a range like "1-5" or a list like "1,15", optionally with a step like "*/15" or "8-18/2"
+#[derive(Clone, Debug)]
+pub struct CronExpression {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    is_day_of_month_restricted: bool,
+    is_day_of_week_restricted: bool
+}
+
+impl CronExpression {
+    pub fn parse(expression: &str) -> Result<CronExpression, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("expected 5 fields (minute, hour, day of month, month and day of week) but got {}", fields.len()));
+        }
+        let days_of_week = Self::parse_field(fields[4], "day of week", 0, 7)?;
+        Ok(CronExpression {
+            minutes: Self::parse_field(fields[0], "minute", 0, 59)?,
+            hours: Self::parse_field(fields[1], "hour", 0, 23)?,
+            days_of_month: Self::parse_field(fields[2], "day of month", 1, 31)?,
+            months: Self::parse_field(fields[3], "month", 1, 12)?,
+            // 7 is another way of writing Sunday
+            days_of_week: (days_of_week | days_of_week >> 7) & 0x7f,
+            is_day_of_month_restricted: !fields[2].starts_with('*'),
+            is_day_of_week_restricted: !fields[4].starts_with('*')
+        })
+    }
+
+    // The first whole minute after the time that matches, going by the machine's local time
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local_time = time.with_timezone(&Local).naive_local();
+        let mut candidate = local_time.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+        let search_end = candidate + TimeDelta::days(MAX_SEARCH_DAYS);
+        while candidate < search_end {
+            if !Self::has(self.months, candidate.month()) {
+                candidate = Self::get_start_of_next_month(candidate)?;
+            }
+            else if !self.matches_day(candidate.date()) {
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            }
+            else if !Self::has(self.hours, candidate.hour()) {
+                candidate = candidate.with_minute(0)? + TimeDelta::hours(1);
+            }
+            else if !Self::has(self.minutes, candidate.minute()) {
+                candidate += TimeDelta::minutes(1);
+            }
+            else {
+                // Times skipped when the clocks go forward don't exist locally
+                match Local.from_local_datetime(&candidate).earliest() {
+                    Some(next_time) => return Some(next_time.with_timezone(&Utc)),
+                    None => candidate += TimeDelta::minutes(1)
+                }
+            }
+        }
+        None
+    }
+
+    // As in cron, a day only has to match one of the two day fields when both are restricted
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let matches_day_of_month = Self::has(self.days_of_month, date.day());
+        let matches_day_of_week = Self::has(self.days_of_week, date.weekday().num_days_from_sunday());
+        if self.is_day_of_month_restricted && self.is_day_of_week_restricted {
+            return matches_day_of_month || matches_day_of_week;
+        }
+        matches_day_of_month && matches_day_of_week
+    }
+
+    fn get_start_of_next_month(time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+        NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+    }
+
+    fn has(values: u64, value: u32) -> bool {
+        values & 1 << value != 0
+    }
+
+    fn parse_field(field: &str, name: &str, min: u32, max: u32) -> Result<u64, String> {
+        let mut values = 0;
+        for item in field.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => (range, step.parse::<usize>().ok().filter(|step| *step > 0)
+                    .ok_or_else(|| format!("expected the {} step to be a number greater than 0 but got \"{}\"", name, step))?),
+                None => (item, 1)
+            };
+            let (start, end) = match (range, range.split_once('-')) {
+                ("*", _) => (min, max),
+                (_, Some((start, end))) => (Self::parse_value(start, name, min, max)?, Self::parse_value(end, name, min, max)?),
+                // "5/15" runs to the end like "5-59/15"
+                (_, None) if step > 1 => (Self::parse_value(range, name, min, max)?, max),
+                (_, None) => {
+                    let value = Self::parse_value(range, name, min, max)?;
+                    (value, value)
+                }
+            };
+            if start > end {
+                return Err(format!("the {} range \"{}\" ends before it starts", name, range));
+            }
+            for value in (start..=end).step_by(step) {
+                values |= 1 << value;
+            }
+        }
+        Ok(values)
+    }
+
+    fn parse_value(value: &str, name: &str, min: u32, max: u32) -> Result<u32, String> {
+        value.parse::<u32>().ok().filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| format!("expected the {} to be from {} to {} but got \"{}\"", name, min, max, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{ DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc };
+    use super::CronExpression;
+
+    // Tests go by the local time like schedules do, so they pass in any time zone away from clock changes
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        let local_time = NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+        Local.from_local_datetime(&local_time).earliest().unwrap().with_timezone(&Utc)
+    }
+
+    fn next_after(expression: &str, time: DateTime<Utc>) -> Option<NaiveDateTime> {
+        CronExpression::parse(expression).unwrap().next_after(time).map(|next_time| next_time.with_timezone(&Local).naive_local())
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0)
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for expression in ["", "* * * *", "* * * * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "* * * * 8", "5-1 * * * *", "*/0 * * * *", "a * * * *", "1,,2 * * * *"] {
+            assert!(CronExpression::parse(expression).is_err(), "\"{}\" should be rejected", expression);
+        }
+    }
+
+    #[test]
+    fn accepts_ranges_lists_and_steps() {
+        for expression in ["* * * * *", "0,30 8-18/2 1-15 */3 1-5", "5/15 * * * 7", "59 23 31 12 0"] {
+            assert!(CronExpression::parse(expression).is_ok(), "\"{}\" should be accepted", expression);
+        }
+    }
+
+    #[test]
+    fn runs_in_the_next_whole_minute() {
+        let time = at(2024, 6, 1, 10, 0) + chrono::TimeDelta::seconds(30);
+        assert_eq!(next_after("* * * * *", time), local(2024, 6, 1, 10, 1));
+        assert_eq!(next_after("0 10 * * *", at(2024, 6, 1, 10, 0)), local(2024, 6, 2, 10, 0));
+    }
+
+    #[test]
+    fn runs_a_step_from_its_start_to_the_end() {
+        let expression = "5/15 * * * *";
+        assert_eq!(next_after(expression, at(2024, 6, 1, 10, 0)), local(2024, 6, 1, 10, 5));
+        assert_eq!(next_after(expression, at(2024, 6, 1, 10, 35)), local(2024, 6, 1, 10, 50));
+        assert_eq!(next_after(expression, at(2024, 6, 1, 10, 50)), local(2024, 6, 1, 11, 5));
+    }
+
+    #[test]
+    fn crosses_month_and_year_boundaries() {
+        assert_eq!(next_after("0 0 1 * *", at(2024, 1, 31, 12, 0)), local(2024, 2, 1, 0, 0));
+        assert_eq!(next_after("30 8 1 1 *", at(2024, 6, 1, 0, 0)), local(2025, 1, 1, 8, 30));
+        assert_eq!(next_after("0 0 31 * *", at(2024, 4, 1, 0, 0)), local(2024, 5, 31, 0, 0));
+    }
+
+    #[test]
+    fn treats_seven_as_sunday() {
+        // The 1st of June 2024 is a Saturday
+        assert_eq!(next_after("0 9 * * 7", at(2024, 6, 1, 0, 0)), local(2024, 6, 2, 9, 0));
+        assert_eq!(next_after("0 9 * * 0", at(2024, 6, 1, 0, 0)), local(2024, 6, 2, 9, 0));
+    }
+
+    #[test]
+    fn matches_either_day_field_when_both_are_restricted() {
+        // The 13th or any Friday; the 11th of October 2024 is a Friday and the 13th a Sunday
+        let expression = "0 12 13 * 5";
+        assert_eq!(next_after(expression, at(2024, 10, 5, 0, 0)), local(2024, 10, 11, 12, 0));
+        assert_eq!(next_after(expression, at(2024, 10, 11, 12, 0)), local(2024, 10, 13, 12, 0));
+    }
+
+    #[test]
+    fn matches_both_day_fields_when_one_is_a_wildcard() {
+        assert_eq!(next_after("0 12 13 * *", at(2024, 10, 5, 0, 0)), local(2024, 10, 13, 12, 0));
+        assert_eq!(next_after("0 12 * * 5", at(2024, 10, 5, 0, 0)), local(2024, 10, 11, 12, 0));
+    }
+
+    #[test]
+    fn finds_leap_days_years_ahead() {
+        assert_eq!(next_after("0 0 29 2 *", at(2024, 3, 1, 0, 0)), local(2028, 2, 29, 0, 0));
+    }
+
+    #[test]
+    fn never_runs_on_impossible_dates() {
+        assert_eq!(next_after("0 0 30 2 *", at(2024, 1, 1, 0, 0)), None);
+        assert_eq!(next_after("0 0 31 4,6,9,11 *", at(2024, 1, 1, 0, 0)), None);
+    }
+}
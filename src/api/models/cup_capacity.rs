@@ -0,0 +1,41 @@
+use uuid::Uuid;
+use serde::Deserialize;
+use serde_json::json;
+use crate::api::models::{ ApiError, ApiErrorCode };
+
+// Order pours are rounded to hundredths of a ml, which may take them this far over
+const ROUNDING_TOLERANCE_ML: f32 = 0.05;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CupOverflowPolicy {
+    Reject,
+    // Pour less so it still fits
+    Scale
+}
+
+// How much may go into a cup, leaving room for ice
+#[derive(Clone, Copy, Debug)]
+pub struct CupCapacity {
+    pub cup_id: Uuid,
+    pub capacity_ml: f32,
+    pub overflow_policy: CupOverflowPolicy
+}
+
+impl CupCapacity {
+    // What to multiply the planned volume by so it fits on top of what's already planned for the cup
+    pub fn fit(&self, already_planned_ml: f32, planned_ml: f32) -> Result<f32, ApiError> {
+        let remaining_ml = (self.capacity_ml - already_planned_ml).max(0.0);
+        if planned_ml <= remaining_ml + ROUNDING_TOLERANCE_ML {
+            return Ok(1.0);
+        }
+        if self.overflow_policy == CupOverflowPolicy::Scale && remaining_ml > ROUNDING_TOLERANCE_ML {
+            return Ok(remaining_ml / planned_ml);
+        }
+        Err(ApiError::with_details(ApiErrorCode::CupWouldOverflow, json!({
+            "cup_id": self.cup_id,
+            "capacity_ml": self.capacity_ml.floor(),
+            "planned_ml": (already_planned_ml + planned_ml).ceil()
+        })))
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+use serde::Serialize;
+use crate::api::models::{ DiagnosticCheck, DiagnosticStatus };
+
+#[derive(Serialize, Clone, Default)]
+pub struct DiagnosticsReport {
+    pub checks: Vec<DiagnosticCheck>
+}
+
+impl DiagnosticsReport {
+    pub fn add(&mut self, name: &str, status: DiagnosticStatus, message: String) {
+        self.checks.push(DiagnosticCheck { name: name.to_string(), status, message });
+    }
+
+    pub fn add_result<T>(&mut self, name: &str, result: &Result<T, String>, pass_message: String) {
+        match result {
+            Ok(_) => self.add(name, DiagnosticStatus::Pass, pass_message),
+            Err(error) => self.add(name, DiagnosticStatus::Fail, error.clone())
+        }
+    }
+
+    pub fn add_skipped(&mut self, name: &str, reason: &str) {
+        self.add(name, DiagnosticStatus::Warn, format!("Skipped because {}", reason));
+    }
+
+    pub fn get_status(&self, name: &str) -> Option<DiagnosticStatus> {
+        self.checks.iter().find(|check| check.name == name).map(|check| check.status)
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.checks.iter().any(|check| check.status == DiagnosticStatus::Fail)
+    }
+}
+
+impl fmt::Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Startup diagnostics:")?;
+        for check in &self.checks {
+            let status = match check.status {
+                DiagnosticStatus::Pass => "PASS",
+                DiagnosticStatus::Warn => "WARN",
+                DiagnosticStatus::Fail => "FAIL"
+            };
+            writeln!(f, "  [{}] {}: {}", status, check.name, check.message)?;
+        }
+        Ok(())
+    }
+}
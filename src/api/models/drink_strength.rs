@@ -0,0 +1,32 @@
+use uuid::Uuid;
+use serde::Serialize;
+use crate::api::models::settings::Drink;
+
+#[derive(Serialize, Clone, Copy)]
+pub struct DrinkStrength {
+    #[serde(rename = "cupId")]
+    pub cup_id: Uuid,
+    #[serde(rename = "totalVolumeMl")]
+    pub total_volume_ml: f32,
+    pub abv: f32,
+    #[serde(rename = "alcoholGrams")]
+    pub alcohol_grams: f32,
+    #[serde(rename = "standardDrinks")]
+    pub standard_drinks: f32
+}
+
+#[derive(Serialize)]
+pub struct MenuDrink {
+    #[serde(flatten)]
+    pub drink: Drink,
+    // Missing when no cup was picked and the drink has no default cup, or it has no ingredients
+    pub strength: Option<DrinkStrength>
+}
+
+// How much of an ingredient goes into a drink
+#[derive(Clone, Copy)]
+pub struct MeasuredIngredient {
+    pub ingredient_id: Uuid,
+    pub ml: f32,
+    pub alcohol_grams: f32
+}
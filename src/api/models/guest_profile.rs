@@ -0,0 +1,47 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GuestProfile {
+    pub id: Uuid,
+    pub name: String,
+    // Orders made with this API key are counted against this guest
+    #[serde(rename = "apiKeyId")]
+    pub api_key_id: Option<Uuid>,
+    #[serde(rename = "bodyWeightKg")]
+    pub body_weight_kg: Option<f32>,
+    // Share of body weight that holds alcohol, typically 0.68 for men and 0.55 for women
+    #[serde(rename = "widmarkFactor")]
+    pub widmark_factor: Option<f32>
+}
+
+#[derive(Deserialize)]
+pub struct NewGuestProfile {
+    pub name: String,
+    #[serde(rename = "apiKeyId")]
+    pub api_key_id: Option<Uuid>,
+    #[serde(rename = "bodyWeightKg")]
+    pub body_weight_kg: Option<f32>,
+    #[serde(rename = "widmarkFactor")]
+    pub widmark_factor: Option<f32>
+}
+
+#[derive(Serialize)]
+pub struct WindowConsumption {
+    #[serde(rename = "windowMinutes")]
+    pub window_minutes: u64,
+    #[serde(rename = "alcoholGrams")]
+    pub alcohol_grams: f32,
+    #[serde(rename = "maxAlcoholGrams")]
+    pub max_alcohol_grams: f32
+}
+
+#[derive(Serialize)]
+pub struct GuestProfileReport {
+    #[serde(flatten)]
+    pub profile: GuestProfile,
+    pub consumption: Vec<WindowConsumption>,
+    // Only an estimate and only available when the body weight is known
+    #[serde(rename = "estimatedBloodAlcoholPercent")]
+    pub estimated_blood_alcohol_percent: Option<f32>
+}
@@ -0,0 +1,12 @@
+use serde::{ Deserialize, Serialize };
+
+// Higher priorities are pumped first, jobs of the same priority in the order they were queued
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent
+}
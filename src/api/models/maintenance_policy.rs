@@ -0,0 +1,40 @@
+use serde::Serialize;
+use crate::api::models::{ MaintenanceTask, PumpUsage };
+
+// A task is due once any of the limits that are set is reached since it was last done
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct MaintenanceThreshold {
+    #[serde(rename = "afterOnHours")]
+    pub after_on_hours: Option<f32>,
+    #[serde(rename = "afterSwitchCycles")]
+    pub after_switch_cycles: Option<u64>,
+    #[serde(rename = "afterMl")]
+    pub after_ml: Option<f32>
+}
+
+impl MaintenanceThreshold {
+    pub fn is_reached(&self, usage: &PumpUsage) -> bool {
+        self.after_on_hours.is_some_and(|on_hours| usage.on_milliseconds as f32 >= on_hours * 60.0 * 60.0 * 1000.0)
+            || self.after_switch_cycles.is_some_and(|switch_cycles| usage.switch_cycles >= switch_cycles)
+            || self.after_ml.is_some_and(|ml| usage.ml >= ml)
+    }
+}
+
+// When the pumps need looking after
+#[derive(Clone, Copy)]
+pub struct MaintenancePolicy {
+    // Since the line was last poured without being cleaned afterwards
+    pub cleaning_due_after_idle_hours: Option<f32>,
+    pub cleaning_due_after_ml: Option<f32>,
+    pub replace_tubing: MaintenanceThreshold,
+    pub replace_pump: MaintenanceThreshold
+}
+
+impl MaintenancePolicy {
+    pub fn get_threshold(&self, maintenance_task: MaintenanceTask) -> &MaintenanceThreshold {
+        match maintenance_task {
+            MaintenanceTask::ReplaceTubing => &self.replace_tubing,
+            MaintenanceTask::ReplacePump => &self.replace_pump
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+use crate::api::models::{ MaintenanceTask, MaintenanceThreshold, PumpUsage };
+
+#[derive(Serialize, Clone)]
+pub struct MaintenanceReminder {
+    #[serde(rename = "pumpNumber")]
+    pub pump_number: u8,
+    pub task: MaintenanceTask,
+    // e.g. "Replace the tubing on pump 3", in the caller's language
+    pub message: String,
+    // Empty when the task was never recorded as done, then the usage counts from when the pump was first used
+    #[serde(rename = "lastDoneAt")]
+    pub last_done_at: Option<u64>,
+    #[serde(rename = "usageSinceDone")]
+    pub usage_since_done: PumpUsage,
+    pub threshold: MaintenanceThreshold
+}
@@ -0,0 +1,29 @@
+use serde::{ Deserialize, Serialize };
+use crate::api::models::PumpUsage;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTask {
+    ReplaceTubing,
+    ReplacePump
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 2] = [MaintenanceTask::ReplaceTubing, MaintenanceTask::ReplacePump];
+
+    pub fn get_resource_name(&self) -> &'static str {
+        match self {
+            MaintenanceTask::ReplaceTubing => "replace_tubing_reminder_message_template",
+            MaintenanceTask::ReplacePump => "replace_pump_reminder_message_template"
+        }
+    }
+}
+
+// When a task was last done on a pump, along with the pump's usage at the time
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct MaintenanceRecord {
+    // Unix milliseconds
+    #[serde(rename = "doneAt")]
+    pub done_at: u64,
+    pub usage: PumpUsage
+}
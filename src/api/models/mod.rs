@@ -1,10 +1,90 @@
 mod pump_state;
+mod pump_record;
+mod pump_line;
+mod pump_heat;
 mod pump_job;
-mod generic_error;
+mod pump_job_kind;
+mod job_priority;
+mod pump_job_move;
+mod queue_policy;
+mod pump_usage;
+mod maintenance_task;
+mod maintenance_policy;
+mod maintenance_reminder;
+mod cleaning_program;
+mod cleaning_run;
+mod pump_group;
+mod queue_estimate;
+mod pump_queue_summary;
+mod cron_expression;
+mod schedule;
+mod api_error;
+mod accepted_locales;
+mod role;
+mod api_key;
+mod caller;
+mod cup_capacity;
+mod pour_amount;
+mod units_of_measure;
+mod config;
+mod diagnostic_check;
+mod diagnostics_report;
+#[cfg(feature = "bff")]
+mod consumption_limit;
+#[cfg(feature = "bff")]
+mod consumption_record;
+#[cfg(feature = "bff")]
+mod guest_profile;
+#[cfg(feature = "bff")]
+mod drink_strength;
+#[cfg(feature = "bff")]
+mod pour_modifiers;
+#[cfg(feature = "bff")]
+mod order;
 #[cfg(feature = "bff")]
 pub mod settings;
 pub mod resources_xml;
 
 pub use pump_state::*;
+pub use pump_record::*;
+pub use pump_line::*;
+pub use pump_heat::*;
 pub use pump_job::*;
-pub use generic_error::*;
+pub use pump_job_kind::*;
+pub use job_priority::*;
+pub use pump_job_move::*;
+pub use queue_policy::*;
+pub use pump_usage::*;
+pub use maintenance_task::*;
+pub use maintenance_policy::*;
+pub use maintenance_reminder::*;
+pub use cleaning_program::*;
+pub use cleaning_run::*;
+pub use pump_group::*;
+pub use queue_estimate::*;
+pub use pump_queue_summary::*;
+pub use cron_expression::*;
+pub use schedule::*;
+pub use api_error::*;
+pub use accepted_locales::*;
+pub use role::*;
+pub use api_key::*;
+pub use caller::*;
+pub use cup_capacity::*;
+pub use pour_amount::*;
+pub use units_of_measure::*;
+pub use config::*;
+pub use diagnostic_check::*;
+pub use diagnostics_report::*;
+#[cfg(feature = "bff")]
+pub use consumption_limit::*;
+#[cfg(feature = "bff")]
+pub use consumption_record::*;
+#[cfg(feature = "bff")]
+pub use guest_profile::*;
+#[cfg(feature = "bff")]
+pub use drink_strength::*;
+#[cfg(feature = "bff")]
+pub use pour_modifiers::*;
+#[cfg(feature = "bff")]
+pub use order::*;
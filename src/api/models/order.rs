@@ -0,0 +1,56 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use crate::api::models::{ JobPriority, PourModifiers };
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NewOrder {
+    #[serde(rename = "drinkId")]
+    pub drink_id: Uuid,
+    // Falls back to the drink's default cup
+    #[serde(rename = "cupId")]
+    pub cup_id: Option<Uuid>,
+    #[serde(rename = "guestId")]
+    pub guest_id: Option<Uuid>,
+    #[serde(default)]
+    pub modifiers: PourModifiers,
+    // Anything above normal needs a bartender key
+    #[serde(default)]
+    pub priority: JobPriority
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct PlannedPour {
+    #[serde(rename = "pumpNumber")]
+    pub pump_number: u8,
+    #[serde(rename = "ingredientId")]
+    pub ingredient_id: Uuid,
+    pub ml: f32,
+    #[serde(rename = "alcoholGrams")]
+    pub alcohol_grams: f32
+}
+
+#[derive(Serialize, Clone)]
+pub struct Order {
+    pub id: Uuid,
+    #[serde(rename = "drinkId")]
+    pub drink_id: Uuid,
+    #[serde(rename = "cupId")]
+    pub cup_id: Uuid,
+    #[serde(rename = "guestId")]
+    pub guest_id: Option<Uuid>,
+    pub pours: Vec<PlannedPour>,
+    #[serde(rename = "alcoholGrams")]
+    pub alcohol_grams: f32,
+    // From when the order was placed, see the pump queue summary for a fresh estimate
+    #[serde(rename = "queuePosition")]
+    pub queue_position: usize,
+    #[serde(rename = "readyInMilliseconds")]
+    pub ready_in_milliseconds: u64,
+    #[serde(rename = "estimatedStartTime")]
+    pub estimated_start_time: u64,
+    #[serde(rename = "estimatedCompletionTime")]
+    pub estimated_completion_time: u64,
+    // Lines that weren't primed when the order was placed, so the first of their pours may come out short
+    #[serde(rename = "unprimedPumpNumbers")]
+    pub unprimed_pump_numbers: Vec<u8>
+}
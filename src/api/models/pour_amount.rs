@@ -0,0 +1,13 @@
+use serde::{ Deserialize, Serialize };
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PourAmount {
+    pub amount: f32,
+    // ml when left out
+    #[serde(default = "default_unit")]
+    pub unit: String
+}
+
+fn default_unit() -> String {
+    "ml".to_string()
+}
@@ -0,0 +1,51 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SizePreset {
+    // The whole drink in a shot glass
+    Shot,
+    // One or two shots of the alcoholic ingredients topped up with the rest
+    Single,
+    Double,
+    #[default]
+    FillCup
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IngredientAdjustment {
+    Extra,
+    Omit
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IngredientModifier {
+    #[serde(rename = "ingredientId")]
+    pub ingredient_id: Uuid,
+    pub adjustment: IngredientAdjustment
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PourModifiers {
+    // Only scales the alcoholic ingredients, e.g. 0.75 for "a bit weaker"
+    #[serde(default = "PourModifiers::default_strength")]
+    pub strength: f32,
+    #[serde(default)]
+    pub size: SizePreset,
+    #[serde(default)]
+    pub ingredients: Vec<IngredientModifier>
+}
+
+impl PourModifiers {
+    fn default_strength() -> f32 {
+        1.0
+    }
+}
+
+impl Default for PourModifiers {
+    fn default() -> Self {
+        PourModifiers { strength: Self::default_strength(), size: SizePreset::default(), ingredients: vec![] }
+    }
+}
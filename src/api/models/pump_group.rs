@@ -0,0 +1,79 @@
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use crate::api::models::{ CupCapacity, JobPriority, PourAmount, PumpJob, PumpJobKind, QueueEstimate };
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NewPumpJob {
+    #[serde(rename = "pumpNumber")]
+    pub pump_number: u8,
+    #[serde(flatten)]
+    pub amount: PourAmount
+}
+
+#[derive(Serialize, Clone)]
+pub struct QueuedPumpJob {
+    #[serde(flatten)]
+    pub job: PumpJob,
+    #[serde(flatten)]
+    pub estimate: QueueEstimate
+}
+
+// The estimate runs from the group's first job starting to its last one finishing
+#[derive(Serialize, Clone)]
+pub struct QueuedPumpGroup {
+    #[serde(rename = "groupId")]
+    pub group_id: Uuid,
+    #[serde(rename = "cupId")]
+    pub cup_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub estimate: QueueEstimate,
+    pub jobs: Vec<QueuedPumpJob>
+}
+
+impl QueuedPumpGroup {
+    // Groups the jobs by group id in queue order, expecting each group's jobs to be next to each other
+    pub fn from_jobs(queued_pump_jobs: &[QueuedPumpJob]) -> Vec<QueuedPumpGroup> {
+        let mut queued_pump_groups: Vec<QueuedPumpGroup> = vec![];
+        for queued_pump_job in queued_pump_jobs {
+            match queued_pump_groups.last_mut().filter(|queued_pump_group| queued_pump_group.group_id == queued_pump_job.job.group_id) {
+                Some(queued_pump_group) => {
+                    queued_pump_group.estimate.completes_in_milliseconds = queued_pump_job.estimate.completes_in_milliseconds;
+                    queued_pump_group.estimate.estimated_completion_time = queued_pump_job.estimate.estimated_completion_time;
+                    queued_pump_group.jobs.push(queued_pump_job.clone());
+                },
+                None => queued_pump_groups.push(QueuedPumpGroup {
+                    group_id: queued_pump_job.job.group_id,
+                    cup_id: queued_pump_job.job.cup_id,
+                    estimate: queued_pump_job.estimate,
+                    jobs: vec![queued_pump_job.clone()]
+                })
+            }
+        }
+        queued_pump_groups
+    }
+}
+
+// What became of a group's jobs, kept for a while after they leave the queue so a cup can still be topped up and
+// whoever queued the group can tell whether it was poured
+#[derive(Clone, Debug)]
+pub struct PumpGroupRecord {
+    pub id: Uuid,
+    pub cup_capacity: Option<CupCapacity>,
+    pub priority: JobPriority,
+    pub requester: Option<Uuid>,
+    pub kind: PumpJobKind,
+    pub planned_ml: f32,
+    pub pumped_ml: f32,
+    // Pumps whose jobs ran in full
+    pub completed_pump_numbers: Vec<u8>,
+    // Jobs still waiting in the queue or pumping
+    pub unfinished_job_count: usize,
+    // Set once a job of the group is stopped or cleared before it ran in full
+    pub is_dropped: bool
+}
+
+impl PumpGroupRecord {
+    pub fn is_finished(&self) -> bool {
+        self.unfinished_job_count == 0
+    }
+}
@@ -0,0 +1,15 @@
+use std::time::Instant;
+
+// How hot the pump has run since it last cooled down, only kept while the API runs
+#[derive(Clone, Copy, Default)]
+pub struct PumpHeat {
+    pub on_milliseconds: u64,
+    pub switched_off_at: Option<Instant>
+}
+
+impl PumpHeat {
+    // A pump that never ran counts as cool
+    pub fn get_idle_milliseconds(&self) -> u64 {
+        self.switched_off_at.map_or(u64::MAX, |switched_off_at| switched_off_at.elapsed().as_millis() as u64)
+    }
+}
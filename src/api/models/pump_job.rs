@@ -1,7 +1,30 @@
+use std::time::Instant;
+use uuid::Uuid;
 use serde::Serialize;
+use crate::api::models::{ JobPriority, PumpJobKind };
 
 #[derive(Serialize, Clone, Copy)]
 pub struct PumpJob {
+    pub id: Uuid,
+    // These two keep the spelling GET /pump_queue always had so existing clients don't break
     pub pump_number: u8,
-    pub duration_in_milliseconds: u64
+    pub duration_in_milliseconds: u64,
+    pub ml: f32,
+    pub kind: PumpJobKind,
+    // Jobs queued together, e.g. the pours of one order
+    #[serde(rename = "groupId")]
+    pub group_id: Uuid,
+    #[serde(rename = "cupId")]
+    pub cup_id: Option<Uuid>,
+    pub priority: JobPriority,
+    // Who the job is poured for, the guest or API key that ordered it
+    pub requester: Option<Uuid>,
+    // Set by the daemon once the pump is switched on
+    #[serde(skip)]
+    pub started_at: Option<Instant>,
+    // Long jobs are pumped in segments with rests in between, these tell how far the job got
+    #[serde(skip)]
+    pub pumped_milliseconds: u64,
+    #[serde(skip)]
+    pub segment_started_at: Option<Instant>
 }
@@ -0,0 +1,14 @@
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PumpJobKind {
+    #[default]
+    Pour,
+    // Fills the line with the dead volume
+    Prime,
+    // Pumps the dead volume out again, e.g. with the intake in water or air
+    Purge,
+    // Part of a cleaning program, so it only pumps water, cleaner or air
+    Clean
+}
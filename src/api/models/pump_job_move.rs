@@ -0,0 +1,11 @@
+use serde::Deserialize;
+use crate::api::models::JobPriority;
+
+#[derive(Deserialize)]
+pub struct PumpJobMove {
+    // Where the job or group should start in the queue, kept within the jobs of the same priority.
+    // Defaults to the front of those
+    pub position: Option<usize>,
+    // Defaults to the priority it was queued with
+    pub priority: Option<JobPriority>
+}
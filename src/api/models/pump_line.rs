@@ -0,0 +1,111 @@
+// How the tubing and pump on each line are built, in pump number order
+#[derive(Clone, Copy)]
+pub struct PumpLine {
+    // What it takes to fill the tubing from the intake to the nozzle
+    pub dead_volume_ml: f32,
+    // How long the pump may run before it has to rest, no limit when empty
+    pub max_on_milliseconds: Option<u64>,
+    pub cool_down_milliseconds: u64,
+    // How long the pump runs backwards after a pour, 0 when it has no direction line
+    pub retract_milliseconds: u64
+}
+
+impl PumpLine {
+    // Only a full cool-down lets the pump run for its whole max on-time again
+    pub fn cool(&self, heat_milliseconds: u64, idle_milliseconds: u64) -> u64 {
+        if idle_milliseconds >= self.cool_down_milliseconds { 0 } else { heat_milliseconds }
+    }
+
+    // How long the pump still has to rest before it may run again
+    pub fn get_rest_milliseconds(&self, heat_milliseconds: u64, idle_milliseconds: u64) -> u64 {
+        match self.max_on_milliseconds {
+            Some(max_on_milliseconds) if self.cool(heat_milliseconds, idle_milliseconds) >= max_on_milliseconds => self.cool_down_milliseconds.saturating_sub(idle_milliseconds),
+            _ => 0
+        }
+    }
+
+    // How long the pump may run in one go from a rested heat
+    pub fn get_segment_milliseconds(&self, heat_milliseconds: u64, remaining_milliseconds: u64) -> u64 {
+        self.max_on_milliseconds.map_or(remaining_milliseconds, |max_on_milliseconds| remaining_milliseconds.min(max_on_milliseconds.saturating_sub(heat_milliseconds)))
+    }
+
+    // How long running the pump for the on-time takes with the rests in between, and the heat it's left with
+    pub fn plan_run(&self, heat_milliseconds: u64, idle_milliseconds: u64, on_milliseconds: u64) -> (u64, u64) {
+        let mut heat_milliseconds = heat_milliseconds;
+        let mut idle_milliseconds = idle_milliseconds;
+        let mut remaining_milliseconds = on_milliseconds;
+        let mut total_milliseconds = 0;
+        loop {
+            // Cooled before every segment like the daemon does, otherwise a pump without a cool-down never gets going again
+            heat_milliseconds = self.cool(heat_milliseconds, idle_milliseconds);
+            let rest_milliseconds = self.get_rest_milliseconds(heat_milliseconds, idle_milliseconds);
+            if rest_milliseconds > 0 {
+                total_milliseconds += rest_milliseconds;
+                heat_milliseconds = 0;
+            }
+            let segment_milliseconds = self.get_segment_milliseconds(heat_milliseconds, remaining_milliseconds);
+            total_milliseconds += segment_milliseconds;
+            heat_milliseconds += segment_milliseconds;
+            remaining_milliseconds -= segment_milliseconds;
+            idle_milliseconds = 0;
+            if remaining_milliseconds == 0 {
+                return (total_milliseconds, heat_milliseconds);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PumpLine;
+
+    fn create_pump_line(max_on_milliseconds: Option<u64>, cool_down_milliseconds: u64) -> PumpLine {
+        PumpLine { dead_volume_ml: 12.0, max_on_milliseconds, cool_down_milliseconds, retract_milliseconds: 0 }
+    }
+
+    #[test]
+    fn cools_only_after_full_cool_down() {
+        let pump_line = create_pump_line(Some(1000), 500);
+        assert_eq!(pump_line.cool(800, 499), 800);
+        assert_eq!(pump_line.cool(800, 500), 0);
+        assert_eq!(create_pump_line(Some(1000), 0).cool(800, 0), 0);
+    }
+
+    #[test]
+    fn rests_only_once_max_on_time_is_reached() {
+        let pump_line = create_pump_line(Some(1000), 500);
+        assert_eq!(pump_line.get_rest_milliseconds(999, 0), 0);
+        assert_eq!(pump_line.get_rest_milliseconds(1000, 0), 500);
+        assert_eq!(pump_line.get_rest_milliseconds(1000, 200), 300);
+        assert_eq!(pump_line.get_rest_milliseconds(1000, 500), 0);
+        assert_eq!(create_pump_line(None, 500).get_rest_milliseconds(u64::MAX, 0), 0);
+    }
+
+    #[test]
+    fn runs_in_one_go_without_limit() {
+        assert_eq!(create_pump_line(None, 500).plan_run(0, 0, 2500), (2500, 2500));
+    }
+
+    #[test]
+    fn rests_between_segments() {
+        // 1000 on, 500 rest, 1000 on, 500 rest, 500 on
+        assert_eq!(create_pump_line(Some(1000), 500).plan_run(0, u64::MAX, 2500), (3500, 500));
+    }
+
+    #[test]
+    fn finishes_warm_pumps_on_time_left() {
+        let pump_line = create_pump_line(Some(1000), 500);
+        // 400 on, 500 rest, 600 on
+        assert_eq!(pump_line.plan_run(600, 0, 1000), (1500, 600));
+        // Idle for part of the cool-down doesn't count
+        assert_eq!(pump_line.plan_run(1000, 200, 100), (400, 100));
+        // A full cool-down starts from scratch
+        assert_eq!(pump_line.plan_run(1000, 500, 1000), (1000, 1000));
+    }
+
+    #[test]
+    fn plans_pumps_without_cool_down() {
+        assert_eq!(create_pump_line(Some(1000), 0).plan_run(0, u64::MAX, 2500), (2500, 500));
+        assert_eq!(create_pump_line(Some(1000), 0).plan_run(1000, 0, 2500), (2500, 500));
+    }
+}
@@ -0,0 +1,14 @@
+use serde::Serialize;
+use crate::api::models::QueuedPumpGroup;
+
+#[derive(Serialize)]
+pub struct PumpQueueSummary {
+    #[serde(rename = "jobCount")]
+    pub job_count: usize,
+    // How long until every queued job has finished
+    #[serde(rename = "drainMilliseconds")]
+    pub drain_milliseconds: u64,
+    #[serde(rename = "estimatedDrainTime")]
+    pub estimated_drain_time: u64,
+    pub groups: Vec<QueuedPumpGroup>
+}
@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+use serde::{ Deserialize, Serialize };
+use crate::api::models::{ MaintenanceRecord, MaintenanceTask, PumpUsage };
+
+// What's remembered about a pump across restarts
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PumpRecord {
+    // The line is filled up to the nozzle, so a pour comes out in full
+    #[serde(rename = "isPrimed", default)]
+    pub is_primed: bool,
+    // Unix milliseconds
+    #[serde(rename = "lastCleanedAt", default)]
+    pub last_cleaned_at: Option<u64>,
+    #[serde(rename = "lastPouredAt", default)]
+    pub last_poured_at: Option<u64>,
+    #[serde(rename = "mlSinceCleaned", default)]
+    pub ml_since_cleaned: f32,
+    // Everything the pump has done since it was first used
+    #[serde(default)]
+    pub usage: PumpUsage,
+    // When each task was last done, so reminders count from there
+    #[serde(default)]
+    pub maintenance: BTreeMap<MaintenanceTask, MaintenanceRecord>
+}
@@ -1,9 +1,20 @@
 use serde::Serialize;
+use crate::api::models::{ PumpHeat, PumpRecord };
 
 #[derive(Serialize, Clone)]
 pub struct PumpState {
     #[serde(rename = "pumpNumber")]
     pub pump_number: u8,
     #[serde(rename = "isRunning")]
-    pub is_running: bool
+    pub is_running: bool,
+    // Worked out when the state is read, see the cleaning due settings in the config
+    #[serde(rename = "isCleaningDue")]
+    pub is_cleaning_due: bool,
+    // Resting so it doesn't overheat, see the duty cycle settings in the config
+    #[serde(rename = "isCoolingDown")]
+    pub is_cooling_down: bool,
+    #[serde(skip)]
+    pub heat: PumpHeat,
+    #[serde(flatten)]
+    pub record: PumpRecord
 }
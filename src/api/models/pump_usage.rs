@@ -0,0 +1,23 @@
+use serde::{ Deserialize, Serialize };
+
+// How much a pump has been used, counted while it's switched on
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct PumpUsage {
+    #[serde(rename = "onMilliseconds", default)]
+    pub on_milliseconds: u64,
+    // Each time the pump was switched on
+    #[serde(rename = "switchCycles", default)]
+    pub switch_cycles: u64,
+    #[serde(default)]
+    pub ml: f32
+}
+
+impl PumpUsage {
+    pub fn since(&self, earlier_usage: &PumpUsage) -> PumpUsage {
+        PumpUsage {
+            on_milliseconds: self.on_milliseconds.saturating_sub(earlier_usage.on_milliseconds),
+            switch_cycles: self.switch_cycles.saturating_sub(earlier_usage.switch_cycles),
+            ml: (self.ml - earlier_usage.ml).max(0.0)
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use serde::Serialize;
+
+// Where something is in the pump queue and when it should start and finish, both from now and as unix milliseconds
+#[derive(Serialize, Clone, Copy)]
+pub struct QueueEstimate {
+    // 0 is the job at the front of the queue, which may already be pumping
+    pub position: usize,
+    #[serde(rename = "startsInMilliseconds")]
+    pub starts_in_milliseconds: u64,
+    #[serde(rename = "completesInMilliseconds")]
+    pub completes_in_milliseconds: u64,
+    #[serde(rename = "estimatedStartTime")]
+    pub estimated_start_time: u64,
+    #[serde(rename = "estimatedCompletionTime")]
+    pub estimated_completion_time: u64
+}
+
+impl QueueEstimate {
+    pub fn new(position: usize, starts_in_milliseconds: u64, completes_in_milliseconds: u64, now_time: u64) -> QueueEstimate {
+        QueueEstimate {
+            position,
+            starts_in_milliseconds,
+            completes_in_milliseconds,
+            estimated_start_time: now_time + starts_in_milliseconds,
+            estimated_completion_time: now_time + completes_in_milliseconds
+        }
+    }
+}
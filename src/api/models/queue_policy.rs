@@ -0,0 +1,7 @@
+// How the pump queue shares its time between the clients ordering drinks
+#[derive(Clone, Copy)]
+pub struct QueuePolicy {
+    // Interleaves the groups of different requesters round-robin within each priority
+    pub is_fair_queueing_enabled: bool,
+    pub max_pending_groups_per_requester: Option<u32>
+}
@@ -0,0 +1,10 @@
+use serde::{ Deserialize, Serialize };
+
+// Ordered so a higher role can do everything a lower one can
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Guest,
+    Bartender,
+    Admin
+}
@@ -0,0 +1,73 @@
+use chrono::{ DateTime, Utc };
+use uuid::Uuid;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use crate::api::models::{ ApiError, ApiErrorCode, Caller, JobPriority, NewPumpJob };
+#[cfg(feature = "bff")]
+use crate::api::models::NewOrder;
+
+// What gets queued when a schedule is due
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduledAction {
+    PumpJobs {
+        jobs: Vec<NewPumpJob>,
+        #[serde(default)]
+        priority: JobPriority
+    },
+    #[cfg(feature = "bff")]
+    Order {
+        order: NewOrder
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewSchedule {
+    #[serde(default)]
+    pub name: String,
+    // Runs once at this time, or is when the recurrence starts if there is one
+    #[serde(rename = "notBefore")]
+    pub not_before: Option<DateTime<Utc>>,
+    pub recurrence: Option<String>,
+    pub action: ScheduledAction
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduleRun {
+    #[serde(rename = "ranAt")]
+    pub ran_at: DateTime<Utc>,
+    // The pump group or order that was queued
+    #[serde(rename = "groupId")]
+    pub group_id: Option<Uuid>,
+    #[serde(rename = "errorCode")]
+    pub error_code: Option<ApiErrorCode>,
+    #[serde(rename = "errorDetails")]
+    pub error_details: Option<Value>
+}
+
+impl ScheduleRun {
+    pub fn new(ran_at: DateTime<Utc>, result: &Result<Uuid, ApiError>) -> ScheduleRun {
+        match result {
+            Ok(group_id) => ScheduleRun { ran_at, group_id: Some(*group_id), error_code: None, error_details: None },
+            Err(error) => ScheduleRun { ran_at, group_id: None, error_code: Some(error.code), error_details: error.details.clone() }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Schedule {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "notBefore")]
+    pub not_before: Option<DateTime<Utc>>,
+    pub recurrence: Option<String>,
+    // Empty once a one-off schedule failed, it's kept until cancelled so the error can be seen
+    #[serde(rename = "nextRunAt")]
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub action: ScheduledAction,
+    // Orders are placed as this caller so the same rules apply as when they're placed right away
+    #[serde(rename = "scheduledBy")]
+    pub scheduled_by: Caller,
+    #[serde(rename = "lastRun")]
+    pub last_run: Option<ScheduleRun>
+}
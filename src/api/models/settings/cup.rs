@@ -8,5 +8,14 @@ pub struct Cup {
     pub image_url: String,
     pub name: String,
     #[serde(rename  = "volumeMl")]
-    pub volume_ml: u16
+    pub volume_ml: u16,
+    // Share of the volume a drink may fill, falling back to default_cup_fill_ratio from the config
+    #[serde(rename = "fillRatio")]
+    pub fill_ratio: Option<f32>
+}
+
+impl Cup {
+    pub fn is_valid(&self) -> bool {
+        self.volume_ml > 0 && self.fill_ratio.is_none_or(|fill_ratio| fill_ratio > 0.0 && fill_ratio <= 1.0)
+    }
 }
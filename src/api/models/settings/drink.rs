@@ -2,7 +2,6 @@ use crate::api::models::settings::IngredientMeasurement;
 use uuid::Uuid;
 use serde::{ Deserialize, Serialize };
 
-const STAR_RATING_MIN: u8 = 0;
 const STAR_RATING_MAX: u8 = 5;
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -22,6 +21,6 @@ pub struct Drink {
 
 impl Drink {
     pub fn is_valid(&self) -> bool {
-        return self.star_rating >= STAR_RATING_MIN && self.star_rating <= STAR_RATING_MAX;
+        self.star_rating <= STAR_RATING_MAX
     }
 }
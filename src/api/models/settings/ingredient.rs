@@ -1,3 +1,4 @@
+use crate::api::models::settings::IngredientCategory;
 use uuid::Uuid;
 use serde::{ Deserialize, Serialize };
 
@@ -6,4 +7,18 @@ pub struct Ingredient {
     pub id: Uuid,
     pub name: String,
     pub modifier: u16,
+    // Alcohol by volume in percent; missing from settings saved before it existed
+    #[serde(default)]
+    pub abv: f32,
+    // Set separately from abv so e.g. alcohol-free beer can be treated as a mixer
+    #[serde(rename = "isAlcoholic", default)]
+    pub is_alcoholic: bool,
+    #[serde(default)]
+    pub category: IngredientCategory
+}
+
+impl Ingredient {
+    pub fn is_valid(&self) -> bool {
+        (0.0..=100.0).contains(&self.abv)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,16 @@
+use serde::{ Deserialize, Serialize };
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IngredientCategory {
+    Spirit,
+    Liqueur,
+    Wine,
+    Beer,
+    Mixer,
+    Juice,
+    Syrup,
+    Bitters,
+    #[default]
+    Other
+}
@@ -3,6 +3,8 @@ mod settings;
 #[cfg(feature = "bff")]
 mod ingredient;
 #[cfg(feature = "bff")]
+mod ingredient_category;
+#[cfg(feature = "bff")]
 mod cup;
 #[cfg(feature = "bff")]
 mod ingredient_measurement;
@@ -17,6 +19,8 @@ pub use settings::*;
 #[cfg(feature = "bff")]
 pub use ingredient::*;
 #[cfg(feature = "bff")]
+pub use ingredient_category::*;
+#[cfg(feature = "bff")]
 pub use cup::*;
 #[cfg(feature = "bff")]
 pub use ingredient_measurement::*;
@@ -12,6 +12,6 @@ pub struct Pump {
 
 impl Pump {
     pub fn is_valid(&self, number_of_pumps: u8) -> bool {
-        return PumpService::pump_number_is_valid(self.pump_number, number_of_pumps);
+        PumpService::pump_number_is_valid(self.pump_number, number_of_pumps)
     }
 }
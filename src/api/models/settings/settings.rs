@@ -27,17 +27,17 @@ impl Settings {
         let mut pump_numbers = vec![];
         // Check that all cups are unique
         for cup in &self.cups {
-            if all_ids.contains(&cup.id) {
+            if !cup.is_valid() || all_ids.contains(&cup.id) {
                 return false;
             }
             all_ids.push(cup.id);
         }
         // Check that all ingredients are unique
         for ingredient in &self.ingredients {
-            if all_ids.contains(&ingredient.id) {
+            if !ingredient.is_valid() || all_ids.contains(&ingredient.id) {
                 return false;
             }
-            all_ids.push(ingredient.id.clone());
+            all_ids.push(ingredient.id);
         }
         // Check that all pumps are valid, unique, have a valid ingredient
         for pump in &self.pumps {
@@ -55,13 +55,13 @@ impl Settings {
             if !drink.is_valid() || all_ids.contains(&drink.id) {
                 return false;
             }
-            all_ids.push(drink.id.clone());
+            all_ids.push(drink.id);
             for ingredient_measurement in &drink.ingredient_measurements {
                 if !all_ids.contains(&ingredient_measurement.ingredient_id) {
                     return false;
                 }
             }
         }
-        return true;
+        true
     }
 }
@@ -0,0 +1,38 @@
+use serde_json::json;
+use crate::api::models::{ ApiError, ApiErrorCode, PourAmount };
+
+#[derive(Clone)]
+pub struct UnitOfMeasure {
+    pub name: String,
+    pub ml: f32
+}
+
+// The units pours can be given in, each defined by how many ml it is
+#[derive(Clone)]
+pub struct UnitsOfMeasure {
+    units: Vec<UnitOfMeasure>
+}
+
+impl UnitsOfMeasure {
+    pub fn new(units: Vec<UnitOfMeasure>) -> UnitsOfMeasure {
+        UnitsOfMeasure { units }
+    }
+
+    // "fl oz", "FL_OZ" and "fl  oz" all name the same unit
+    pub fn normalize_name(name: &str) -> String {
+        name.split(|character: char| character.is_whitespace() || character == '_')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join("_")
+            .to_lowercase()
+    }
+
+    pub fn to_ml(&self, pour_amount: &PourAmount) -> Result<f32, ApiError> {
+        let name = Self::normalize_name(&pour_amount.unit);
+        let unit = self.units.iter().find(|unit| unit.name == name).ok_or_else(|| ApiError::with_details(ApiErrorCode::UnitOfMeasureUnknown, json!({
+            "unit": pour_amount.unit,
+            "units": self.units.iter().map(|unit| unit.name.clone()).collect::<Vec<String>>().join(", ")
+        })))?;
+        Ok(pour_amount.amount * unit.ml)
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::{ Arc, Mutex };
+use serde_json::json;
+use uuid::Uuid;
+use crate::api::models::{ ApiError, ApiErrorCode, Caller, JobPriority, Role, NewOrder, Order, PlannedPour, PourModifiers };
+use crate::api::models::settings::{ Cup, Drink, Settings };
+use crate::api::{ DrinkService, GuestService, PumpService, SettingsService };
+
+pub struct OrderService {
+    settings_service: Arc<SettingsService>,
+    pump_service: Arc<Mutex<PumpService>>,
+    guest_service: Arc<GuestService>,
+    drink_service: Arc<DrinkService>
+}
+
+impl OrderService {
+    pub fn new(settings_service: Arc<SettingsService>, pump_service: Arc<Mutex<PumpService>>, guest_service: Arc<GuestService>, drink_service: Arc<DrinkService>) -> OrderService {
+        OrderService { settings_service, pump_service, guest_service, drink_service }
+    }
+
+    pub fn place_order(&self, caller: &Caller, new_order: NewOrder) -> Result<Order, ApiError> {
+        let settings = self.settings_service.settings.read().unwrap().clone();
+        let drink = settings.drinks.iter().find(|drink| drink.id == new_order.drink_id)
+            .ok_or_else(|| ApiError::with_details(ApiErrorCode::DrinkNotFound, json!({ "drink_id": new_order.drink_id })))?;
+        let cup_id = new_order.cup_id.or(drink.default_cup_id)
+            .ok_or_else(|| ApiError::new(ApiErrorCode::CupNotSelected))?;
+        let cup = settings.cups.iter().find(|cup| cup.id == cup_id)
+            .ok_or_else(|| ApiError::with_details(ApiErrorCode::CupNotFound, json!({ "cup_id": cup_id })))?;
+        if new_order.priority > JobPriority::Normal && caller.role < Role::Bartender {
+            return Err(ApiError::with_details(ApiErrorCode::InsufficientRole, json!({ "role": caller.role, "required_role": Role::Bartender })));
+        }
+        let pours = self.fit_pour_plan(&settings, cup, self.create_pour_plan(&settings, drink, cup, &new_order.modifiers)?)?;
+        let alcohol_grams = pours.iter().map(|pour| pour.alcohol_grams).sum();
+        let guest_id = self.guest_service.resolve_guest_id(caller, new_order.guest_id)?;
+        let pump_amounts: Vec<(u8, f32)> = pours.iter().map(|pour| (pour.pump_number, pour.ml)).collect();
+        let order_id = Uuid::new_v4();
+        let requester = guest_id.or(caller.api_key_id);
+        let pump_numbers: Vec<u8> = pours.iter().map(|pour| pour.pump_number).collect();
+        let unprimed_pump_numbers = self.pump_service.lock().unwrap().get_unprimed_pump_numbers(&pump_numbers);
+        if !unprimed_pump_numbers.is_empty() {
+            log::warn!("Order {} is poured on unprimed pumps {:?} so it may come out short", order_id, unprimed_pump_numbers);
+        }
+        let queued_pump_group = self.guest_service.pour_within_limits(guest_id, order_id, alcohol_grams, || self.pump_service.lock().unwrap().enqueue_pump_group(order_id, &pump_amounts, Some(self.drink_service.get_cup_capacity(cup)), new_order.priority, requester))?;
+        Ok(Order {
+            id: order_id,
+            drink_id: drink.id,
+            cup_id: cup.id,
+            guest_id,
+            pours,
+            alcohol_grams,
+            queue_position: queued_pump_group.estimate.position,
+            ready_in_milliseconds: queued_pump_group.estimate.completes_in_milliseconds,
+            estimated_start_time: queued_pump_group.estimate.estimated_start_time,
+            estimated_completion_time: queued_pump_group.estimate.estimated_completion_time,
+            unprimed_pump_numbers
+        })
+    }
+
+    // Rounds each ingredient's share of the cup to hundredths of a ml on the pump holding it
+    pub fn create_pour_plan(&self, settings: &Settings, drink: &Drink, cup: &Cup, pour_modifiers: &PourModifiers) -> Result<Vec<PlannedPour>, ApiError> {
+        let mut pours = vec![];
+        for measured_ingredient in self.drink_service.measure_ingredients(settings, drink, cup, pour_modifiers)? {
+            let ingredient = settings.ingredients.iter().find(|ingredient| ingredient.id == measured_ingredient.ingredient_id);
+            let pump = settings.pumps.iter().find(|pump| pump.ingredient_id == Some(measured_ingredient.ingredient_id));
+            let (ingredient, pump) = match (ingredient, pump) {
+                (Some(ingredient), Some(pump)) => (ingredient, pump),
+                _ => return Err(ApiError::with_details(ApiErrorCode::IngredientNotOnPump, json!({
+                    "ingredient": ingredient.map_or_else(|| measured_ingredient.ingredient_id.to_string(), |ingredient| ingredient.name.clone())
+                })))
+            };
+            let ml = Self::round_ml(measured_ingredient.ml);
+            if ml <= 0.0 {
+                continue;
+            }
+            pours.push(PlannedPour {
+                pump_number: pump.pump_number,
+                ingredient_id: ingredient.id,
+                ml,
+                alcohol_grams: DrinkService::get_alcohol_grams(ml, ingredient.abv)
+            });
+        }
+        Ok(pours)
+    }
+
+    // Makes sure the pours fit the cup, shrinking each of them alike if the overflow policy allows it
+    fn fit_pour_plan(&self, settings: &Settings, cup: &Cup, pours: Vec<PlannedPour>) -> Result<Vec<PlannedPour>, ApiError> {
+        let planned_ml: f32 = pours.iter().map(|pour| pour.ml).sum();
+        let factor = self.drink_service.get_cup_capacity(cup).fit(0.0, planned_ml)?;
+        if factor >= 1.0 {
+            return Ok(pours);
+        }
+        Ok(pours.into_iter()
+            .filter_map(|pour| {
+                let ml = Self::round_ml(pour.ml * factor);
+                let abv = settings.ingredients.iter().find(|ingredient| ingredient.id == pour.ingredient_id).map_or(0.0, |ingredient| ingredient.abv);
+                (ml > 0.0).then(|| PlannedPour { ml, alcohol_grams: DrinkService::get_alcohol_grams(ml, abv), ..pour })
+            })
+            .collect())
+    }
+
+    fn round_ml(ml: f32) -> f32 {
+        (ml * 100.0).round() / 100.0
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(feature = "use-gpio")]
+use gpio_cdev::LineHandle;
+#[cfg(not(feature = "use-gpio"))]
+use crate::api::mock::LineHandle;
+
+// Switches one pump through its relay, and through the direction line of its H-bridge when it has one so it can run backwards
+pub struct PumpDriver {
+    motor_line_handle: LineHandle,
+    // Low runs the pump forwards, high backwards
+    direction_line_handle: Option<LineHandle>,
+    is_relay_inverted: bool
+}
+
+impl PumpDriver {
+    pub fn new(motor_line_handle: LineHandle, direction_line_handle: Option<LineHandle>, is_relay_inverted: bool) -> PumpDriver {
+        PumpDriver { motor_line_handle, direction_line_handle, is_relay_inverted }
+    }
+
+    // What the relay line is set to, for the logs
+    pub fn get_motor_value(&self, is_on: bool) -> u8 {
+        (is_on != self.is_relay_inverted) as u8
+    }
+
+    pub fn switch_on(&self) {
+        self.motor_line_handle.set_value(self.get_motor_value(true)).unwrap();
+    }
+
+    // Leaves the pump facing forwards again
+    pub fn switch_off(&self) {
+        self.motor_line_handle.set_value(self.get_motor_value(false)).unwrap();
+        if let Some(direction_line_handle) = &self.direction_line_handle {
+            direction_line_handle.set_value(0).unwrap();
+        }
+    }
+
+    // Runs the pump backwards to pull the liquid back from the nozzle so it doesn't drip, until it's switched off again.
+    // Tells whether it can run backwards at all
+    pub fn start_retracting(&self) -> bool {
+        let direction_line_handle = match &self.direction_line_handle {
+            Some(direction_line_handle) => direction_line_handle,
+            None => return false
+        };
+        direction_line_handle.set_value(1).unwrap();
+        self.switch_on();
+        true
+    }
+}
@@ -1,49 +1,82 @@
-use std::collections::VecDeque;
+use std::collections::{ BTreeMap, HashMap, HashSet, VecDeque };
+use std::fs;
+use std::path::{ Path, PathBuf };
 use std::thread;
-use std::time::Duration;
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
 use std::sync::{ Mutex, Arc, Condvar };
-#[cfg(feature = "use-gpio")]
-use gpio_cdev::LineHandle;
 use serde_json::json;
-#[cfg(not(feature = "use-gpio"))]
-use crate::api::mock::LineHandle;
-use crate::api::models::{ PumpState, PumpJob };
-use crate::api::ResourceService;
+use uuid::Uuid;
+use crate::api::models::{ PumpState, PumpJob, ApiError, Config, ApiErrorCode, CupCapacity, JobPriority, MaintenancePolicy, MaintenanceRecord, MaintenanceReminder, MaintenanceTask, PumpJobKind, PumpGroupRecord, PumpJobMove, PumpLine, PumpQueueSummary, PumpRecord, QueuePolicy, QueueEstimate, QueuedPumpGroup, QueuedPumpJob };
+use crate::api::{ PumpDriver, ResourceService };
+
+// Groups that have left the queue are forgotten oldest first beyond this many
+const MAX_PUMP_GROUP_RECORDS: usize = 256;
 
 pub struct PumpService {
     resource_service: Arc<ResourceService>,
-    is_relay_inverted: bool,
     pump_pin_numbers: Vec<u32>,
+    pump_lines: Vec<PumpLine>,
+    pumps_file_path: PathBuf,
     ms_per_ml: u64,
     daemon_thread: Option<thread::JoinHandle<()>>,
-    line_handles: Arc<Mutex<Vec<LineHandle>>>,
+    pump_drivers: Arc<Mutex<Vec<PumpDriver>>>,
     pump_states: Arc<Mutex<Vec<PumpState>>>,
     pump_queue: Arc<Mutex<VecDeque<PumpJob>>>,
-    run_daemon_pair: Arc<(Mutex<bool>, Condvar)>
+    // Locked after the pump queue when both are needed
+    pump_group_records: Arc<Mutex<VecDeque<PumpGroupRecord>>>,
+    run_daemon_pair: Arc<(Mutex<bool>, Condvar)>,
+    queue_policy: QueuePolicy,
+    maintenance_policy: MaintenancePolicy,
+    // Only cleaning jobs are taken while a cleaning program runs
+    is_cleaning: bool
+}
+
+// Everything the daemon thread shares with the service
+struct DaemonContext {
+    resource_service: Arc<ResourceService>,
+    pump_queue_arc: Arc<Mutex<VecDeque<PumpJob>>>,
+    pump_group_records_arc: Arc<Mutex<VecDeque<PumpGroupRecord>>>,
+    pump_drivers_arc: Arc<Mutex<Vec<PumpDriver>>>,
+    pump_states_arc: Arc<Mutex<Vec<PumpState>>>,
+    should_run_daemon_pair: Arc<(Mutex<bool>, Condvar)>,
+    pump_lines: Vec<PumpLine>,
+    pumps_file_path: PathBuf
+}
+
+// What every job queued together shares
+struct PumpGroup {
+    id: Uuid,
+    cup_capacity: Option<CupCapacity>,
+    priority: JobPriority,
+    requester: Option<Uuid>,
+    kind: PumpJobKind
 }
 
 impl PumpService {
-    pub fn new(
-        resource_service: Arc<ResourceService>,
-        is_relay_inverted: bool,
-        pump_pin_numbers: Vec<u32>,
-        ms_per_ml: u64,
-        daemon_thread: Option<thread::JoinHandle<()>>,
-        line_handles: Arc<Mutex<Vec<LineHandle>>>,
-        pump_states: Arc<Mutex<Vec<PumpState>>>,
-        pump_queue: Arc<Mutex<VecDeque<PumpJob>>>,
-        run_daemon_pair: Arc<(Mutex<bool>, Condvar)>
-    ) -> PumpService {
+    pub fn new(resource_service: Arc<ResourceService>, config: &Config, pump_drivers: Vec<PumpDriver>, pump_states: Vec<PumpState>) -> PumpService {
         PumpService {
             resource_service,
-            is_relay_inverted,
-            pump_pin_numbers,
-            ms_per_ml,
-            daemon_thread,
-            line_handles,
-            pump_states,
-            pump_queue,
-            run_daemon_pair
+            pump_pin_numbers: config.ordered_pump_pin_numbers.clone(),
+            pump_lines: config.pump_lines.clone(),
+            pumps_file_path: config.pumps_file_path.clone(),
+            ms_per_ml: config.milliseconds_per_ml,
+            daemon_thread: None,
+            pump_drivers: Arc::new(Mutex::new(pump_drivers)), // Revise all 3 of these with RwLock where appropriate
+            pump_states: Arc::new(Mutex::new(pump_states)),
+            pump_queue: Arc::new(Mutex::new(VecDeque::new())),
+            pump_group_records: Arc::new(Mutex::new(VecDeque::new())),
+            run_daemon_pair: Arc::new((Mutex::new(true), Condvar::new())),
+            queue_policy: QueuePolicy {
+                is_fair_queueing_enabled: config.is_fair_queueing_enabled,
+                max_pending_groups_per_requester: config.max_pending_orders_per_requester
+            },
+            maintenance_policy: MaintenancePolicy {
+                cleaning_due_after_idle_hours: config.cleaning_due_after_idle_hours,
+                cleaning_due_after_ml: config.cleaning_due_after_ml,
+                replace_tubing: config.replace_tubing_threshold,
+                replace_pump: config.replace_pump_threshold
+            },
+            is_cleaning: false
         }
     }
 
@@ -55,64 +88,559 @@ impl PumpService {
         pump_number > 0 && pump_number <= number_of_pumps
     }
     
-    pub fn enqueue_pump(&self, pump_number: u8, ml_to_pump: u32) -> Result<Vec<PumpJob>, String> {
-        if !PumpService::pump_number_is_valid(pump_number, self.get_number_of_pumps()) {
-            let invalid_pump_number_message = self.resource_service.get_resource_string_by_name("invalid_pump_number_error_message").unwrap();
-            return Err(invalid_pump_number_message);
-        }
-        if ml_to_pump == 0 {
-            let invalid_ml_to_pump_message = self.resource_service.get_resource_string_by_name("invalid_ml_to_pump_error_message").unwrap();
-            return Err(invalid_ml_to_pump_message);
-        }
-        let duration_in_milliseconds = ml_to_pump as u64 * self.ms_per_ml;
-        let message_data = &json!({"pump_number": pump_number, "milliseconds": duration_in_milliseconds});
-        let scheduling_pump_message = self.resource_service.render_resource_template_string_by_name("scheduling_pump_info_message_template", message_data).unwrap();
-        log::info!("{}", scheduling_pump_message);
-        self.pump_queue.lock().unwrap().push_back(PumpJob {
-            pump_number,
-            duration_in_milliseconds
-        });
-        let pump_queue = self.get_pump_queue();
+    // A pour is checked against its own cup unless it tops up a group, which it then joins with that group's cup,
+    // priority and requester. The group may already be poured, then what it poured is still in the cup
+    pub fn enqueue_pump(&self, pump_number: u8, ml_to_pump: f32, cup_capacity: Option<CupCapacity>, group_id: Option<Uuid>, priority: JobPriority) -> Result<Vec<QueuedPumpJob>, ApiError> {
+        self.validate_pump_amounts(&[(pump_number, ml_to_pump)])?;
+        self.validate_accepting_jobs(PumpJobKind::Pour)?;
+        let mut pump_queue = self.pump_queue.lock().unwrap();
+        let mut pump_group = PumpGroup { id: Uuid::new_v4(), cup_capacity, priority, requester: None, kind: PumpJobKind::Pour };
+        let mut already_planned_ml = 0.0;
+        if let Some(group_id) = group_id {
+            let pump_group_record = self.get_group_record(group_id).filter(|pump_group_record| pump_group_record.kind == PumpJobKind::Pour)
+                .ok_or_else(|| ApiError::with_details(ApiErrorCode::PumpJobNotFound, json!({ "id": group_id })))?;
+            let group_cup_id = pump_group_record.cup_capacity.map(|cup_capacity| cup_capacity.cup_id);
+            if let Some(cup_capacity) = cup_capacity.filter(|cup_capacity| Some(cup_capacity.cup_id) != group_cup_id) {
+                return Err(ApiError::with_details(ApiErrorCode::PumpGroupCupMismatch, json!({ "group_id": group_id, "cup_id": cup_capacity.cup_id })));
+            }
+            pump_group = PumpGroup { id: group_id, cup_capacity: pump_group_record.cup_capacity, priority: pump_group_record.priority, requester: pump_group_record.requester, kind: PumpJobKind::Pour };
+            // A job's ml only counts as pumped once it's done, until then it's still in the queue
+            let queued_ml: f32 = pump_queue.iter().filter(|pump_job| pump_job.group_id == group_id).map(|pump_job| pump_job.ml).sum();
+            already_planned_ml = pump_group_record.pumped_ml + queued_ml;
+        }
+        let mut ml_to_pump = ml_to_pump;
+        if let Some(cup_capacity) = pump_group.cup_capacity {
+            ml_to_pump *= cup_capacity.fit(already_planned_ml, ml_to_pump)?;
+        }
+        self.push_pump_jobs(&mut pump_queue, &[(pump_number, ml_to_pump)], &pump_group);
+        let queued_pump_jobs = self.estimate_queue(&pump_queue);
+        drop(pump_queue);
+        self.notify_daemon(false);
+        Ok(queued_pump_jobs)
+    }
+
+    // Either every job is queued back to back as one group or none are
+    pub fn enqueue_pump_group(&self, group_id: Uuid, pump_amounts: &[(u8, f32)], cup_capacity: Option<CupCapacity>, priority: JobPriority, requester: Option<Uuid>) -> Result<QueuedPumpGroup, ApiError> {
+        if pump_amounts.is_empty() {
+            return Err(ApiError::new(ApiErrorCode::PumpJobsMissing));
+        }
+        self.validate_pump_amounts(pump_amounts)?;
+        self.validate_accepting_jobs(PumpJobKind::Pour)?;
+        let mut pump_amounts = pump_amounts.to_vec();
+        if let Some(cup_capacity) = cup_capacity {
+            let planned_ml: f32 = pump_amounts.iter().map(|(_, ml_to_pump)| ml_to_pump).sum();
+            let factor = cup_capacity.fit(0.0, planned_ml)?;
+            pump_amounts.iter_mut().for_each(|(_, ml_to_pump)| *ml_to_pump *= factor);
+        }
+        let pump_group = PumpGroup { id: group_id, cup_capacity, priority, requester, kind: PumpJobKind::Pour };
+        self.queue_pump_group(&pump_group, &pump_amounts)
+    }
+
+    // Primes or purges the lines one after another with their dead volume
+    pub fn enqueue_line_jobs(&self, pump_numbers: &[u8], kind: PumpJobKind, priority: JobPriority) -> Result<QueuedPumpGroup, ApiError> {
+        let mut pump_amounts = vec![];
+        for &pump_number in pump_numbers {
+            if !PumpService::pump_number_is_valid(pump_number, self.get_number_of_pumps()) {
+                return Err(ApiError::with_details(ApiErrorCode::PumpNotFound, json!({ "pump_number": pump_number })));
+            }
+            pump_amounts.push((pump_number, self.pump_lines[pump_number as usize - 1].dead_volume_ml));
+        }
+        self.validate_accepting_jobs(kind)?;
+        let pump_group = PumpGroup { id: Uuid::new_v4(), cup_capacity: None, priority, requester: None, kind };
+        self.queue_pump_group(&pump_group, &pump_amounts)
+    }
+
+    // Queued ahead of everything, though nothing else should be queued while cleaning
+    pub fn enqueue_cleaning_jobs(&self, pump_amounts: &[(u8, f32)]) -> Result<QueuedPumpGroup, ApiError> {
+        if pump_amounts.is_empty() {
+            return Err(ApiError::new(ApiErrorCode::PumpJobsMissing));
+        }
+        self.validate_pump_amounts(pump_amounts)?;
+        self.validate_accepting_jobs(PumpJobKind::Clean)?;
+        let pump_group = PumpGroup { id: Uuid::new_v4(), cup_capacity: None, priority: JobPriority::Urgent, requester: None, kind: PumpJobKind::Clean };
+        self.queue_pump_group(&pump_group, pump_amounts)
+    }
+
+    pub fn get_dead_volume_ml(&self, pump_number: u8) -> Option<f32> {
+        self.pump_lines.get((pump_number as usize).checked_sub(1)?).map(|pump_line| pump_line.dead_volume_ml)
+    }
+
+    pub fn is_group_queued(&self, group_id: Uuid) -> bool {
+        self.pump_queue.lock().unwrap().iter().any(|pump_job| pump_job.group_id == group_id)
+    }
+
+    pub fn get_group_record(&self, group_id: Uuid) -> Option<PumpGroupRecord> {
+        self.pump_group_records.lock().unwrap().iter().find(|pump_group_record| pump_group_record.id == group_id).cloned()
+    }
+
+    // Drops the group's jobs, switching off its pump if one of them is running
+    pub fn cancel_group(&self, group_id: Uuid) {
+        let mut pump_queue = self.pump_queue.lock().unwrap();
+        let dropped_pump_jobs: Vec<PumpJob> = pump_queue.iter().filter(|pump_job| pump_job.group_id == group_id).copied().collect();
+        pump_queue.retain(|pump_job| pump_job.group_id != group_id);
+        Self::record_dropped_jobs(&self.pump_group_records, &dropped_pump_jobs);
+        drop(pump_queue);
         self.notify_daemon(false);
-        Ok(pump_queue)
     }
 
-    pub fn get_pump_state(&self, pump_number: u8) -> Result<PumpState, String> {
+    // Lines are filled with water, cleaner or air while cleaning, so drinks have to wait until it's done
+    pub fn start_cleaning(&mut self) -> Result<(), ApiError> {
+        self.validate_accepting_jobs(PumpJobKind::Clean)?;
+        let pump_job_count = self.pump_queue.lock().unwrap().len();
+        if pump_job_count > 0 {
+            return Err(ApiError::with_details(ApiErrorCode::PumpQueueNotEmpty, json!({ "job_count": pump_job_count })));
+        }
+        self.is_cleaning = true;
+        Ok(())
+    }
+
+    // Records the lines as cleaned unless the program didn't finish
+    pub fn finish_cleaning(&mut self, cleaned_pump_numbers: &[u8]) {
+        self.is_cleaning = false;
+        if cleaned_pump_numbers.is_empty() {
+            return;
+        }
+        let now_time = Self::get_now_time();
+        let mut pump_states = self.pump_states.lock().unwrap();
+        for pump_state in pump_states.iter_mut().filter(|pump_state| cleaned_pump_numbers.contains(&pump_state.pump_number)) {
+            pump_state.record.last_cleaned_at = Some(now_time);
+            pump_state.record.ml_since_cleaned = 0.0;
+        }
+        Self::save_pump_records(&self.pumps_file_path, &pump_states);
+    }
+
+    fn queue_pump_group(&self, pump_group: &PumpGroup, pump_amounts: &[(u8, f32)]) -> Result<QueuedPumpGroup, ApiError> {
+        let mut pump_queue = self.pump_queue.lock().unwrap();
+        Self::check_pending_group_limit(&self.queue_policy, &pump_queue, pump_group.requester)?;
+        let first_position = self.push_pump_jobs(&mut pump_queue, pump_amounts, pump_group);
+        let queued_pump_jobs = self.estimate_queue(&pump_queue);
+        drop(pump_queue);
+        self.notify_daemon(false);
+        Ok(QueuedPumpGroup::from_jobs(&queued_pump_jobs[first_position..first_position + pump_amounts.len()]).remove(0))
+    }
+
+    // Moves a pending job, or every job of a group, to another position and optionally another priority
+    pub fn move_pending(&self, id: Uuid, pump_job_move: PumpJobMove) -> Result<Vec<QueuedPumpJob>, ApiError> {
+        let mut pump_queue = self.pump_queue.lock().unwrap();
+        let matches_id = |pump_job: &PumpJob| pump_job.id == id || pump_job.group_id == id;
+        if pump_queue.iter().any(|pump_job| matches_id(pump_job) && pump_job.started_at.is_some()) {
+            return Err(ApiError::with_details(ApiErrorCode::PumpJobRunning, json!({ "id": id })));
+        }
+        let mut moved_pump_jobs: Vec<PumpJob> = pump_queue.iter().filter(|pump_job| matches_id(pump_job)).copied().collect();
+        if moved_pump_jobs.is_empty() {
+            return Err(ApiError::with_details(ApiErrorCode::PumpJobNotFound, json!({ "id": id })));
+        }
+        let priority = pump_job_move.priority.unwrap_or(moved_pump_jobs[0].priority);
+        moved_pump_jobs.iter_mut().for_each(|pump_job| pump_job.priority = priority);
+        pump_queue.retain(|pump_job| !matches_id(pump_job));
+        // The queue stays sorted by priority so the job can only go between the others of its priority
+        let first_pending_index = Self::get_first_pending_index(&pump_queue);
+        let first_index = first_pending_index + pump_queue.iter().skip(first_pending_index).filter(|pump_job| pump_job.priority > priority).count();
+        let last_index = Self::get_insert_index(&self.queue_policy, &pump_queue, priority, None).1;
+        let mut index = pump_job_move.position.map_or(first_index, |position| position.clamp(first_index, last_index));
+        // Never split another group
+        while index > first_index && index < pump_queue.len() && pump_queue[index - 1].group_id == pump_queue[index].group_id {
+            index -= 1;
+        }
+        for (offset, pump_job) in moved_pump_jobs.into_iter().enumerate() {
+            pump_queue.insert(index + offset, pump_job);
+        }
+        Ok(self.estimate_queue(&pump_queue))
+    }
+
+    // Switches off the pump that's running now and drops every job, rather than waiting for the running one to finish
+    pub fn stop(&self) -> Vec<QueuedPumpJob> {
+        let mut pump_queue = self.pump_queue.lock().unwrap();
+        let dropped_pump_jobs: Vec<PumpJob> = pump_queue.drain(..).collect();
+        Self::record_dropped_jobs(&self.pump_group_records, &dropped_pump_jobs);
+        let queued_pump_jobs = self.estimate_queue(&pump_queue);
+        drop(pump_queue);
+        self.notify_daemon(false);
+        queued_pump_jobs
+    }
+
+    pub fn validate_pump_amounts(&self, pump_amounts: &[(u8, f32)]) -> Result<(), ApiError> {
+        for &(pump_number, ml_to_pump) in pump_amounts {
+            if !PumpService::pump_number_is_valid(pump_number, self.get_number_of_pumps()) {
+                return Err(ApiError::with_details(ApiErrorCode::PumpNotFound, json!({ "pump_number": pump_number })));
+            }
+            if !ml_to_pump.is_finite() || ml_to_pump <= 0.0 {
+                return Err(ApiError::new(ApiErrorCode::MlToPumpNotPositive));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_accepting_jobs(&self, kind: PumpJobKind) -> Result<(), ApiError> {
+        if !self.is_daemon_alive() {
+            return Err(ApiError::new(ApiErrorCode::PumpQueueNotProcessing));
+        }
+        if self.is_cleaning && kind != PumpJobKind::Clean {
+            return Err(ApiError::new(ApiErrorCode::PumpsBeingCleaned));
+        }
+        Ok(())
+    }
+
+    // Queues the jobs behind everything of a higher priority, returning where the first one went
+    fn push_pump_jobs(&self, pump_queue: &mut VecDeque<PumpJob>, pump_amounts: &[(u8, f32)], pump_group: &PumpGroup) -> usize {
+        let first_index = match pump_queue.iter().rposition(|pump_job| pump_job.group_id == pump_group.id) {
+            // Topping up a group keeps it together
+            Some(last_group_index) => last_group_index + 1,
+            None => Self::get_insert_index(&self.queue_policy, pump_queue, pump_group.priority, pump_group.requester).0
+        };
+        for (offset, &(pump_number, ml_to_pump)) in pump_amounts.iter().enumerate() {
+            let duration_in_milliseconds = (ml_to_pump * self.ms_per_ml as f32).round() as u64;
+            let message_data = &json!({"pump_number": pump_number, "milliseconds": duration_in_milliseconds});
+            let scheduling_pump_message = self.resource_service.render_resource_template_string_by_name("scheduling_pump_info_message_template", message_data);
+            log::info!("{}", scheduling_pump_message);
+            pump_queue.insert(first_index + offset, PumpJob {
+                id: Uuid::new_v4(),
+                pump_number,
+                duration_in_milliseconds,
+                ml: ml_to_pump,
+                kind: pump_group.kind,
+                group_id: pump_group.id,
+                cup_id: pump_group.cup_capacity.map(|cup_capacity| cup_capacity.cup_id),
+                priority: pump_group.priority,
+                requester: pump_group.requester,
+                started_at: None,
+                pumped_milliseconds: 0,
+                segment_started_at: None
+            });
+        }
+        Self::record_queued_jobs(&self.pump_group_records, pump_amounts, pump_group);
+        first_index
+    }
+
+    // Called with the pump queue locked so the records change along with it
+    fn record_queued_jobs(pump_group_records_arc: &Mutex<VecDeque<PumpGroupRecord>>, pump_amounts: &[(u8, f32)], pump_group: &PumpGroup) {
+        let planned_ml: f32 = pump_amounts.iter().map(|(_, ml_to_pump)| ml_to_pump).sum();
+        let mut pump_group_records = pump_group_records_arc.lock().unwrap();
+        if let Some(pump_group_record) = pump_group_records.iter_mut().find(|pump_group_record| pump_group_record.id == pump_group.id) {
+            pump_group_record.planned_ml += planned_ml;
+            pump_group_record.unfinished_job_count += pump_amounts.len();
+            return;
+        }
+        if pump_group_records.len() >= MAX_PUMP_GROUP_RECORDS {
+            if let Some(index) = pump_group_records.iter().position(PumpGroupRecord::is_finished) {
+                pump_group_records.remove(index);
+            }
+        }
+        pump_group_records.push_back(PumpGroupRecord {
+            id: pump_group.id,
+            cup_capacity: pump_group.cup_capacity,
+            priority: pump_group.priority,
+            requester: pump_group.requester,
+            kind: pump_group.kind,
+            planned_ml,
+            pumped_ml: 0.0,
+            completed_pump_numbers: vec![],
+            unfinished_job_count: pump_amounts.len(),
+            is_dropped: false
+        });
+    }
+
+    // The daemon finishes the record of the job it's pumping once the pump is off, so what it got through is counted
+    fn record_dropped_jobs(pump_group_records_arc: &Mutex<VecDeque<PumpGroupRecord>>, dropped_pump_jobs: &[PumpJob]) {
+        let mut pump_group_records = pump_group_records_arc.lock().unwrap();
+        for pump_job in dropped_pump_jobs {
+            if let Some(pump_group_record) = pump_group_records.iter_mut().find(|pump_group_record| pump_group_record.id == pump_job.group_id) {
+                pump_group_record.is_dropped = true;
+                if pump_job.started_at.is_none() {
+                    pump_group_record.unfinished_job_count = pump_group_record.unfinished_job_count.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    fn record_finished_job(pump_group_records_arc: &Mutex<VecDeque<PumpGroupRecord>>, pump_job: &PumpJob, pumped_ml: f32, is_completed: bool) {
+        if let Ok(mut pump_group_records) = pump_group_records_arc.lock() {
+            if let Some(pump_group_record) = pump_group_records.iter_mut().find(|pump_group_record| pump_group_record.id == pump_job.group_id) {
+                pump_group_record.pumped_ml += pumped_ml;
+                pump_group_record.unfinished_job_count = pump_group_record.unfinished_job_count.saturating_sub(1);
+                match is_completed {
+                    true if !pump_group_record.completed_pump_numbers.contains(&pump_job.pump_number) => pump_group_record.completed_pump_numbers.push(pump_job.pump_number),
+                    true => {},
+                    false => pump_group_record.is_dropped = true
+                }
+            }
+        }
+    }
+
+    // A running job is never preempted, and neither is the rest of its group so a drink isn't split up
+    fn get_first_pending_index(pump_queue: &VecDeque<PumpJob>) -> usize {
+        match pump_queue.front() {
+            Some(running_pump_job) if running_pump_job.started_at.is_some() => pump_queue.iter().take_while(|pump_job| pump_job.group_id == running_pump_job.group_id).count(),
+            _ => 0
+        }
+    }
+
+    // Where a new group of the priority goes, and where the jobs of that priority end. With fair queueing the group
+    // goes in the requester's next round, so each requester gets one group poured per round
+    fn get_insert_index(queue_policy: &QueuePolicy, pump_queue: &VecDeque<PumpJob>, priority: JobPriority, requester: Option<Uuid>) -> (usize, usize) {
+        let first_pending_index = Self::get_first_pending_index(pump_queue);
+        let group_starts: Vec<(usize, &PumpJob)> = pump_queue.iter().enumerate().skip(first_pending_index)
+            .filter(|(index, pump_job)| *index == first_pending_index || pump_queue[index - 1].group_id != pump_job.group_id)
+            .collect();
+        let end_index = group_starts.iter().find(|(_, pump_job)| pump_job.priority < priority).map_or(pump_queue.len(), |(index, _)| *index);
+        if !queue_policy.is_fair_queueing_enabled {
+            return (end_index, end_index);
+        }
+        let same_priority_group_starts: Vec<&(usize, &PumpJob)> = group_starts.iter().filter(|(_, pump_job)| pump_job.priority == priority).collect();
+        let requester_round = same_priority_group_starts.iter().filter(|(_, pump_job)| pump_job.requester == requester).count();
+        let mut rounds: HashMap<Option<Uuid>, usize> = HashMap::new();
+        for (index, pump_job) in same_priority_group_starts {
+            let round = rounds.entry(pump_job.requester).or_insert(0);
+            if *round > requester_round {
+                return (*index, end_index);
+            }
+            *round += 1;
+        }
+        (end_index, end_index)
+    }
+
+    // Clients without a requester, e.g. raw pours, aren't limited
+    fn check_pending_group_limit(queue_policy: &QueuePolicy, pump_queue: &VecDeque<PumpJob>, requester: Option<Uuid>) -> Result<(), ApiError> {
+        if let Some((requester, max_pending_groups)) = requester.zip(queue_policy.max_pending_groups_per_requester) {
+            let pending_group_ids: HashSet<Uuid> = pump_queue.iter().filter(|pump_job| pump_job.requester == Some(requester)).map(|pump_job| pump_job.group_id).collect();
+            if pending_group_ids.len() >= max_pending_groups as usize {
+                return Err(ApiError::with_details(ApiErrorCode::PendingOrderLimitReached, json!({ "max_pending_orders": max_pending_groups })));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_pump_state(&self, pump_number: u8) -> Result<PumpState, ApiError> {
         if !PumpService::pump_number_is_valid(pump_number, self.get_number_of_pumps()) {
-            let invalid_pump_number_message = self.resource_service.get_resource_string_by_name("invalid_pump_number_error_message").unwrap();
-            return Err(invalid_pump_number_message);
+            return Err(ApiError::with_details(ApiErrorCode::PumpNotFound, json!({ "pump_number": pump_number })));
         }
-        Ok(self.pump_states.lock().unwrap()[pump_number as usize - 1].clone())
+        Ok(self.get_pump_states().remove(pump_number as usize - 1))
     }
 
     pub fn get_pump_states(&self) -> Vec<PumpState> {
-        self.pump_states.lock().unwrap().clone()
+        let now_time = Self::get_now_time();
+        self.pump_states.lock().unwrap().iter()
+            .zip(&self.pump_lines)
+            .map(|(pump_state, pump_line)| PumpState {
+                is_cleaning_due: self.is_cleaning_due(&pump_state.record, now_time),
+                is_cooling_down: !pump_state.is_running && pump_line.get_rest_milliseconds(pump_state.heat.on_milliseconds, pump_state.heat.get_idle_milliseconds()) > 0,
+                ..pump_state.clone()
+            })
+            .collect()
+    }
+
+    pub fn get_cleaning_due_pump_numbers(&self) -> Vec<u8> {
+        self.get_pump_states().iter().filter(|pump_state| pump_state.is_cleaning_due).map(|pump_state| pump_state.pump_number).collect()
+    }
+
+    // When something was left standing in the line too long since it was last poured, or too much has gone through it since it was cleaned
+    fn is_cleaning_due(&self, pump_record: &PumpRecord, now_time: u64) -> bool {
+        let is_idle_too_long = self.maintenance_policy.cleaning_due_after_idle_hours.zip(pump_record.last_poured_at)
+            .is_some_and(|(idle_hours, last_poured_at)| {
+                let is_cleaned_since = pump_record.last_cleaned_at.is_some_and(|last_cleaned_at| last_cleaned_at >= last_poured_at);
+                !is_cleaned_since && now_time.saturating_sub(last_poured_at) as f32 >= idle_hours * 60.0 * 60.0 * 1000.0
+            });
+        let has_poured_too_much = self.maintenance_policy.cleaning_due_after_ml.is_some_and(|due_after_ml| pump_record.ml_since_cleaned >= due_after_ml);
+        is_idle_too_long || has_poured_too_much
+    }
+
+    // Messages are in the first of the locales there are strings for
+    pub fn get_maintenance_reminders(&self, locales: &[String]) -> Vec<MaintenanceReminder> {
+        let pump_states = self.pump_states.lock().unwrap();
+        let mut maintenance_reminders = vec![];
+        for pump_state in pump_states.iter() {
+            for maintenance_task in MaintenanceTask::ALL {
+                let threshold = *self.maintenance_policy.get_threshold(maintenance_task);
+                let maintenance_record = pump_state.record.maintenance.get(&maintenance_task);
+                let usage_since_done = maintenance_record.map_or(pump_state.record.usage, |maintenance_record| pump_state.record.usage.since(&maintenance_record.usage));
+                if !threshold.is_reached(&usage_since_done) {
+                    continue;
+                }
+                let message_data = json!({ "pump_number": pump_state.pump_number });
+                maintenance_reminders.push(MaintenanceReminder {
+                    pump_number: pump_state.pump_number,
+                    task: maintenance_task,
+                    message: self.resource_service.render_localized_resource_template_string_by_name(locales, maintenance_task.get_resource_name(), &message_data),
+                    last_done_at: maintenance_record.map(|maintenance_record| maintenance_record.done_at),
+                    usage_since_done,
+                    threshold
+                });
+            }
+        }
+        maintenance_reminders
+    }
+
+    // Reminders for the task count from the pump's usage now
+    pub fn record_maintenance(&self, pump_number: u8, maintenance_task: MaintenanceTask) -> Result<PumpState, ApiError> {
+        if !PumpService::pump_number_is_valid(pump_number, self.get_number_of_pumps()) {
+            return Err(ApiError::with_details(ApiErrorCode::PumpNotFound, json!({ "pump_number": pump_number })));
+        }
+        {
+            let mut pump_states = self.pump_states.lock().unwrap();
+            let pump_record = &mut pump_states[pump_number as usize - 1].record;
+            let maintenance_record = MaintenanceRecord { done_at: Self::get_now_time(), usage: pump_record.usage };
+            pump_record.maintenance.insert(maintenance_task, maintenance_record);
+            Self::save_pump_records(&self.pumps_file_path, &pump_states);
+        }
+        self.get_pump_state(pump_number)
+    }
+
+    #[cfg(feature = "bff")]
+    pub fn get_unprimed_pump_numbers(&self, pump_numbers: &[u8]) -> Vec<u8> {
+        let pump_states = self.pump_states.lock().unwrap();
+        pump_numbers.iter().copied()
+            .filter(|pump_number| (*pump_number as usize).checked_sub(1).and_then(|index| pump_states.get(index)).is_some_and(|pump_state| !pump_state.record.is_primed))
+            .collect()
+    }
+
+    // For when a line gets a new bottle, so its first pour isn't trusted to come out in full
+    #[cfg(feature = "bff")]
+    pub fn mark_unprimed(&self, pump_numbers: &[u8]) {
+        let mut pump_states = self.pump_states.lock().unwrap();
+        for pump_state in pump_states.iter_mut().filter(|pump_state| pump_numbers.contains(&pump_state.pump_number)) {
+            pump_state.record.is_primed = false;
+        }
+        Self::save_pump_records(&self.pumps_file_path, &pump_states);
+    }
+
+    // Priming fills the line and purging or cleaning empties it. Pouring at least the dead volume fills it too, just with a short first pour
+    fn record_completed_job(pump_states: &mut [PumpState], pump_lines: &[PumpLine], pump_job: &PumpJob) {
+        let index = pump_job.pump_number as usize - 1;
+        let pump_record = &mut pump_states[index].record;
+        match pump_job.kind {
+            PumpJobKind::Prime => pump_record.is_primed = true,
+            PumpJobKind::Purge | PumpJobKind::Clean => pump_record.is_primed = false,
+            PumpJobKind::Pour => pump_record.is_primed |= pump_job.ml >= pump_lines[index].dead_volume_ml
+        }
+        if matches!(pump_job.kind, PumpJobKind::Pour | PumpJobKind::Prime) {
+            pump_record.last_poured_at = Some(Self::get_now_time());
+            pump_record.ml_since_cleaned += pump_job.ml;
+        }
+    }
+
+    // Counts the time the pump was actually on, so a stopped job only adds what it got through. Running backwards wears the pump
+    // just the same but doesn't pump anything
+    fn record_pump_run(pump_states: &mut [PumpState], pump_job: &PumpJob, on_milliseconds: u64, retract_milliseconds: u64, switch_cycles: u64) {
+        let pump_usage = &mut pump_states[pump_job.pump_number as usize - 1].record.usage;
+        pump_usage.on_milliseconds += on_milliseconds + retract_milliseconds;
+        pump_usage.switch_cycles += switch_cycles + (retract_milliseconds > 0) as u64;
+        pump_usage.ml += Self::get_pumped_ml(pump_job, on_milliseconds);
+    }
+
+    fn get_pumped_ml(pump_job: &PumpJob, on_milliseconds: u64) -> f32 {
+        let pumped_ratio = match pump_job.duration_in_milliseconds {
+            0 => 1.0,
+            duration_in_milliseconds => (on_milliseconds as f32 / duration_in_milliseconds as f32).min(1.0)
+        };
+        pump_job.ml * pumped_ratio
+    }
+
+    // Losing a record isn't worth stopping the pumps for, so failures are only logged
+    fn save_pump_records(pumps_file_path: &Path, pump_states: &[PumpState]) {
+        let pump_records: BTreeMap<u8, &PumpRecord> = pump_states.iter().map(|pump_state| (pump_state.pump_number, &pump_state.record)).collect();
+        let result = serde_json::to_string_pretty(&pump_records).map_err(|error| error.to_string())
+            .and_then(|pump_records_json| {
+                if let Some(pumps_directory) = pumps_file_path.parent() {
+                    fs::create_dir_all(pumps_directory).map_err(|error| error.to_string())?;
+                }
+                fs::write(pumps_file_path, pump_records_json).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            log::error!("Couldn't save {}: {}", pumps_file_path.display(), error);
+        }
+    }
+
+    pub fn get_pump_queue(&self) -> Vec<QueuedPumpJob> {
+        self.estimate_queue(&self.pump_queue.lock().unwrap())
+    }
+
+    pub fn get_pump_queue_summary(&self) -> PumpQueueSummary {
+        let queued_pump_jobs = self.get_pump_queue();
+        let now_time = Self::get_now_time();
+        let drain_milliseconds = queued_pump_jobs.last().map_or(0, |queued_pump_job| queued_pump_job.estimate.completes_in_milliseconds);
+        PumpQueueSummary {
+            job_count: queued_pump_jobs.len(),
+            drain_milliseconds,
+            estimated_drain_time: now_time + drain_milliseconds,
+            groups: QueuedPumpGroup::from_jobs(&queued_pump_jobs)
+        }
     }
 
-    pub fn get_pump_queue(&self) -> Vec<PumpJob> {
-        Vec::from(self.pump_queue.lock().unwrap().clone())
+    // A running job is left to finish along with the rest of its group, the same as when moving jobs, so a drink
+    // isn't cut off halfway
+    pub fn clear_pending_jobs(&self) -> Vec<QueuedPumpJob> {
+        let mut pump_queue = self.pump_queue.lock().unwrap();
+        let first_pending_index = Self::get_first_pending_index(&pump_queue);
+        let dropped_pump_jobs: Vec<PumpJob> = pump_queue.drain(first_pending_index..).collect();
+        Self::record_dropped_jobs(&self.pump_group_records, &dropped_pump_jobs);
+        self.estimate_queue(&pump_queue)
+    }
+
+    // Each job starts once everything ahead of it is done, counting only what's left of the one pumping now along with the rests
+    // pumps need so they don't overheat
+    fn estimate_queue(&self, pump_queue: &VecDeque<PumpJob>) -> Vec<QueuedPumpJob> {
+        let now = Instant::now();
+        let now_time = Self::get_now_time();
+        // For each pump its heat, how long it had been idle and when, counting from now
+        let mut pump_heats: Vec<(u64, u64, u64)> = self.pump_states.lock().unwrap().iter()
+            .map(|pump_state| (pump_state.heat.on_milliseconds, pump_state.heat.get_idle_milliseconds(), 0))
+            .collect();
+        let mut starts_in_milliseconds = 0;
+        pump_queue.iter().enumerate()
+            .map(|(position, pump_job)| {
+                let index = pump_job.pump_number as usize - 1;
+                let (heat_milliseconds, idle_milliseconds, switched_off_in_milliseconds) = pump_heats[index];
+                let mut remaining_milliseconds = pump_job.duration_in_milliseconds.saturating_sub(pump_job.pumped_milliseconds);
+                let (heat_milliseconds, idle_milliseconds) = match pump_job.segment_started_at {
+                    Some(segment_started_at) => {
+                        let segment_elapsed_milliseconds = now.duration_since(segment_started_at).as_millis() as u64;
+                        remaining_milliseconds = remaining_milliseconds.saturating_sub(segment_elapsed_milliseconds);
+                        (heat_milliseconds + segment_elapsed_milliseconds, 0)
+                    },
+                    None => (heat_milliseconds, idle_milliseconds.saturating_add(starts_in_milliseconds - switched_off_in_milliseconds))
+                };
+                let pump_line = &self.pump_lines[index];
+                let (mut run_milliseconds, mut heat_after_milliseconds) = pump_line.plan_run(heat_milliseconds, idle_milliseconds, remaining_milliseconds);
+                if pump_job.kind == PumpJobKind::Pour {
+                    run_milliseconds += pump_line.retract_milliseconds;
+                    heat_after_milliseconds += pump_line.retract_milliseconds;
+                }
+                let estimate = QueueEstimate::new(position, starts_in_milliseconds, starts_in_milliseconds + run_milliseconds, now_time);
+                starts_in_milliseconds += run_milliseconds;
+                pump_heats[index] = (heat_after_milliseconds, 0, starts_in_milliseconds);
+                QueuedPumpJob { job: *pump_job, estimate }
+            })
+            .collect()
+    }
+
+    fn get_now_time() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or(0)
     }
         
+    pub fn is_daemon_alive(&self) -> bool {
+        match &self.daemon_thread {
+            Some(daemon_thread) => !daemon_thread.is_finished(),
+            None => false
+        }
+    }
+
     pub fn start_daemon(&mut self) {
-        if !self.daemon_thread.is_none() {
+        if self.daemon_thread.is_some() {
             return;
         }
-        let resource_service = self.resource_service.clone();
-        let is_relay_inverted = self.is_relay_inverted.clone();
-        let pump_queue_arc = self.pump_queue.clone();
-        let line_handles_arc = self.line_handles.clone();
-        let pump_states_arc = self.pump_states.clone();
-        let run_daemon_pair = self.run_daemon_pair.clone();
-        let thread_handle = thread::spawn(move || {
-            PumpService::process_queue(
-                resource_service,
-                is_relay_inverted, pump_queue_arc,
-                line_handles_arc, pump_states_arc,
-                run_daemon_pair
-            );
-        });
+        let daemon_context = DaemonContext {
+            resource_service: self.resource_service.clone(),
+            pump_queue_arc: self.pump_queue.clone(),
+            pump_group_records_arc: self.pump_group_records.clone(),
+            pump_drivers_arc: self.pump_drivers.clone(),
+            pump_states_arc: self.pump_states.clone(),
+            should_run_daemon_pair: self.run_daemon_pair.clone(),
+            pump_lines: self.pump_lines.clone(),
+            pumps_file_path: self.pumps_file_path.clone()
+        };
+        let thread_handle = thread::spawn(move || PumpService::process_queue(daemon_context));
         self.daemon_thread = Some(thread_handle);
-        let started_daemon_thread_message = self.resource_service.get_resource_string_by_name("daemon_thread_started_message").unwrap();
+        let started_daemon_thread_message = self.resource_service.get_resource_string_by_name("daemon_thread_started_message");
         log::info!("{}", started_daemon_thread_message);
     }
     
@@ -120,7 +648,7 @@ impl PumpService {
         self.notify_daemon(true);
         if let Some(daemon_thread) = self.daemon_thread.take() {
             daemon_thread.join().unwrap();
-            let killed_daemon_thread_message = self.resource_service.get_resource_string_by_name("daemon_thread_killed_message").unwrap();
+            let killed_daemon_thread_message = self.resource_service.get_resource_string_by_name("daemon_thread_killed_message");
             log::info!("{}", killed_daemon_thread_message);
         }
     }
@@ -133,88 +661,394 @@ impl PumpService {
         cvar.notify_one();
     }
 
-    fn process_queue(
-        resource_service: Arc<ResourceService>,
-        is_relay_inverted: bool,
-        pump_queue_arc: Arc<Mutex<VecDeque<PumpJob>>>,
-        line_handles_arc: Arc<Mutex<Vec<LineHandle>>>,
-        pump_states_arc: Arc<Mutex<Vec<PumpState>>>,
-        should_run_daemon_pair: Arc<(Mutex<bool>, Condvar)>
-    ) {
-        let starting_daemon_thread_message = resource_service.get_resource_string_by_name("starting_daemon_thread_message").unwrap();
+    fn process_queue(daemon_context: DaemonContext) {
+        let DaemonContext { resource_service, pump_queue_arc, pump_group_records_arc, pump_drivers_arc, pump_states_arc, should_run_daemon_pair, pump_lines, pumps_file_path } = daemon_context;
+        let starting_daemon_thread_message = resource_service.get_resource_string_by_name("starting_daemon_thread_message");
         log::debug!("{}", starting_daemon_thread_message);
         let (should_run_daemon_mutex, cvar) = &*should_run_daemon_pair;
         let mut should_run_daemon = false;
         if let Ok(should_run_daemon_guard) = should_run_daemon_mutex.lock() {
-            should_run_daemon = should_run_daemon_guard.clone();
+            should_run_daemon = *should_run_daemon_guard;
         }
         while should_run_daemon {
             // Get first in line job, leave in queue until done processing
             let mut pump_job_to_process: Option<PumpJob> = None;
-            if let Ok(pump_queue) = pump_queue_arc.lock() {
-                pump_job_to_process = pump_queue.get(0).copied();
+            if let Ok(mut pump_queue) = pump_queue_arc.lock() {
+                pump_job_to_process = PumpService::start_front_job(&mut pump_queue);
             }
             while let Some(pump_job) = pump_job_to_process {
                 let index = pump_job.pump_number as usize - 1;
-                let duration: Duration;
-                if let Ok(mut locked_pump_states) = pump_states_arc.lock() {
-                    let processing_job_message_data = &json!({"pump_number": pump_job.pump_number, "milliseconds": pump_job.duration_in_milliseconds});
-                    let processing_job_message = resource_service.render_resource_template_string_by_name("processing_job_info_message_template", processing_job_message_data).unwrap();
-                    log::info!("{}", processing_job_message);
-                    locked_pump_states[index].is_running = true;
-                    duration = Duration::from_millis(pump_job.duration_in_milliseconds);
-                }
-                else {
-                    let failed_to_lock_pump_states_error_message = resource_service.get_resource_string_by_name("failed_to_lock_pump_states_error_message").unwrap();
-                    panic!("{}", failed_to_lock_pump_states_error_message);
+                let pump_line = pump_lines[index];
+                let processing_job_message_data = &json!({"pump_number": pump_job.pump_number, "milliseconds": pump_job.duration_in_milliseconds});
+                let processing_job_message = resource_service.render_resource_template_string_by_name("processing_job_info_message_template", processing_job_message_data);
+                log::info!("{}", processing_job_message);
+                let mut remaining_milliseconds = pump_job.duration_in_milliseconds;
+                let mut on_milliseconds = 0;
+                let mut switch_cycles = 0;
+                let mut is_completed = false;
+                // The pump runs for at most its max on-time in one go, resting in between so it doesn't overheat
+                loop {
+                    let rest_milliseconds = match pump_states_arc.lock() {
+                        Ok(locked_pump_states) => pump_line.get_rest_milliseconds(locked_pump_states[index].heat.on_milliseconds, locked_pump_states[index].heat.get_idle_milliseconds()),
+                        Err(_) => {
+                            let failed_to_lock_pump_states_error_message = resource_service.get_resource_string_by_name("failed_to_lock_pump_states_error_message");
+                            panic!("{}", failed_to_lock_pump_states_error_message);
+                        }
+                    };
+                    if rest_milliseconds > 0 {
+                        let cooling_down_pump_message_data = &json!({ "pump_number": pump_job.pump_number, "milliseconds": rest_milliseconds });
+                        let cooling_down_pump_message = resource_service.render_resource_template_string_by_name("cooling_down_pump_info_message_template", cooling_down_pump_message_data);
+                        log::info!("{}", cooling_down_pump_message);
+                        if !PumpService::wait_for_pump_job(&should_run_daemon_pair, &pump_queue_arc, pump_job.id, Duration::from_millis(rest_milliseconds)) {
+                            break;
+                        }
+                    }
+                    let mut segment_milliseconds = remaining_milliseconds;
+                    if let Ok(mut locked_pump_states) = pump_states_arc.lock() {
+                        let pump_heat = &mut locked_pump_states[index].heat;
+                        pump_heat.on_milliseconds = pump_line.cool(pump_heat.on_milliseconds, pump_heat.get_idle_milliseconds());
+                        segment_milliseconds = pump_line.get_segment_milliseconds(pump_heat.on_milliseconds, remaining_milliseconds);
+                        locked_pump_states[index].is_running = true;
+                    }
+                    PumpService::update_running_job(&pump_queue_arc, pump_job.id, |running_pump_job| running_pump_job.segment_started_at = Some(Instant::now()));
+                    let mut is_segment_completed = false;
+                    let mut segment_on_milliseconds = 0;
+                    // The drivers are only locked to switch the lines, not while the pump runs
+                    let is_switched_on = pump_drivers_arc.lock().is_ok_and(|locked_pump_drivers| {
+                        let setting_pump_high_message_data = &json!({ "pump_number": pump_job.pump_number, "value": locked_pump_drivers[index].get_motor_value(true) });
+                        let setting_pump_high_message = resource_service.render_resource_template_string_by_name("setting_pump_high_info_message_template", setting_pump_high_message_data);
+                        log::debug!("{}", setting_pump_high_message);
+                        locked_pump_drivers[index].switch_on();
+                        true
+                    });
+                    if is_switched_on {
+                        let switched_on_at = Instant::now();
+                        is_segment_completed = PumpService::wait_for_pump_job(&should_run_daemon_pair, &pump_queue_arc, pump_job.id, Duration::from_millis(segment_milliseconds));
+                        if let Ok(locked_pump_drivers) = pump_drivers_arc.lock() {
+                            let setting_pump_low_message_data = &json!({ "pump_number": pump_job.pump_number, "value": locked_pump_drivers[index].get_motor_value(false) });
+                            let setting_pump_low_message = resource_service.render_resource_template_string_by_name("setting_pump_low_info_message_template", setting_pump_low_message_data);
+                            log::debug!("{}", setting_pump_low_message);
+                            locked_pump_drivers[index].switch_off();
+                        }
+                        segment_on_milliseconds = switched_on_at.elapsed().as_millis() as u64;
+                    }
+                    on_milliseconds += segment_on_milliseconds;
+                    switch_cycles += 1;
+                    if let Ok(mut locked_pump_states) = pump_states_arc.lock() {
+                        locked_pump_states[index].is_running = false;
+                        locked_pump_states[index].heat.on_milliseconds += segment_on_milliseconds;
+                        locked_pump_states[index].heat.switched_off_at = Some(Instant::now());
+                    }
+                    PumpService::update_running_job(&pump_queue_arc, pump_job.id, |running_pump_job| {
+                        running_pump_job.segment_started_at = None;
+                        running_pump_job.pumped_milliseconds += segment_milliseconds;
+                    });
+                    if !is_segment_completed {
+                        break;
+                    }
+                    remaining_milliseconds -= segment_milliseconds;
+                    if remaining_milliseconds == 0 {
+                        is_completed = true;
+                        break;
+                    }
                 }
-                if let Ok(locked_line_handles) = line_handles_arc.lock() {
-                    let mut high = 1;
-                    let mut low = 0;
-                    if is_relay_inverted {
-                        high = 0;
-                        low = 1;
+                // Even a stopped pour leaves liquid in the nozzle
+                let mut retract_milliseconds = 0;
+                if pump_job.kind == PumpJobKind::Pour && on_milliseconds > 0 && pump_line.retract_milliseconds > 0 {
+                    let retracting_pump_message_data = &json!({ "pump_number": pump_job.pump_number, "milliseconds": pump_line.retract_milliseconds });
+                    let retracting_pump_message = resource_service.render_resource_template_string_by_name("retracting_pump_info_message_template", retracting_pump_message_data);
+                    log::debug!("{}", retracting_pump_message);
+                    // A pour that was stopped is already gone from the queue, then only killing the daemon cuts the retract short
+                    let is_job_queued = pump_queue_arc.lock().is_ok_and(|pump_queue| pump_queue.iter().any(|queued_pump_job| queued_pump_job.id == pump_job.id));
+                    if pump_drivers_arc.lock().is_ok_and(|locked_pump_drivers| locked_pump_drivers[index].start_retracting()) {
+                        let retract_started_at = Instant::now();
+                        let retract_duration = Duration::from_millis(pump_line.retract_milliseconds);
+                        match is_job_queued {
+                            true => PumpService::wait_for_pump_job(&should_run_daemon_pair, &pump_queue_arc, pump_job.id, retract_duration),
+                            false => PumpService::wait_for_daemon(&should_run_daemon_pair, retract_duration)
+                        };
+                        if let Ok(locked_pump_drivers) = pump_drivers_arc.lock() {
+                            locked_pump_drivers[index].switch_off();
+                        }
+                        retract_milliseconds = retract_started_at.elapsed().as_millis() as u64;
                     }
-                    let setting_pump_high_message_data = &json!({ "pump_number": pump_job.pump_number, "value": high });
-                    let setting_pump_high_message = resource_service.render_resource_template_string_by_name("setting_pump_high_info_message_template", setting_pump_high_message_data).unwrap();
-                    log::debug!("{}", setting_pump_high_message);
-                    locked_line_handles[index].set_value(high).unwrap();
-                    thread::sleep(duration);
-                    let setting_pump_low_message_data = &json!({ "pump_number": pump_job.pump_number, "value": low });
-                    let setting_pump_low_message = resource_service.render_resource_template_string_by_name("setting_pump_low_info_message_template", setting_pump_low_message_data).unwrap();
-                    log::debug!("{}", setting_pump_low_message);
-                    locked_line_handles[index].set_value(low).unwrap();
                 }
                 if let Ok(mut locked_pump_states) = pump_states_arc.lock() {
-                    locked_pump_states[pump_job.pump_number as usize - 1].is_running = false;
+                    if retract_milliseconds > 0 {
+                        locked_pump_states[index].heat.on_milliseconds += retract_milliseconds;
+                        locked_pump_states[index].heat.switched_off_at = Some(Instant::now());
+                    }
+                    PumpService::record_pump_run(&mut locked_pump_states, &pump_job, on_milliseconds, retract_milliseconds, switch_cycles);
+                    if is_completed {
+                        PumpService::record_completed_job(&mut locked_pump_states, &pump_lines, &pump_job);
+                    }
+                    PumpService::save_pump_records(&pumps_file_path, &locked_pump_states);
                 }
                 if let Ok(mut pump_queue) = pump_queue_arc.lock() {
                     // Discard the job we just processed
-                    pump_queue.pop_front();
+                    pump_queue.retain(|queued_pump_job| queued_pump_job.id != pump_job.id);
+                    let pumped_ml = match is_completed {
+                        true => pump_job.ml,
+                        false => PumpService::get_pumped_ml(&pump_job, on_milliseconds)
+                    };
+                    PumpService::record_finished_job(&pump_group_records_arc, &pump_job, pumped_ml, is_completed);
                     // Get next in line job for processing if any
-                    pump_job_to_process = pump_queue.get(0).copied();
+                    pump_job_to_process = PumpService::start_front_job(&mut pump_queue);
                 }
                 // Intermediate checking for daemon killed
                 if let Ok(should_run_daemon_guard) = should_run_daemon_mutex.lock() {
                     if !*should_run_daemon_guard {
-                        let daemon_killed_while_processing_message = resource_service.get_resource_string_by_name("daemon_killed_while_processing_message").unwrap();
+                        let daemon_killed_while_processing_message = resource_service.get_resource_string_by_name("daemon_killed_while_processing_message");
                         log::debug!("{}", daemon_killed_while_processing_message);
                         return;
                     }
                 }
             }
-            let finished_processing_queue_info_message = resource_service.get_resource_string_by_name("finished_processing_queue_info_message").unwrap();
+            let finished_processing_queue_info_message = resource_service.get_resource_string_by_name("finished_processing_queue_info_message");
             log::debug!("{}", finished_processing_queue_info_message);
             if let Ok(should_run_daemon_guard) = should_run_daemon_mutex.lock() {
-                let waiting_message = resource_service.get_resource_string_by_name("waiting_for_should_run_daemon_guard_message").unwrap();
+                let waiting_message = resource_service.get_resource_string_by_name("waiting_for_should_run_daemon_guard_message");
                 log::debug!("{}", waiting_message);
                 let temp_should_run_daemon_guard = cvar.wait(should_run_daemon_guard).unwrap();
-                should_run_daemon = temp_should_run_daemon_guard.clone();
-                let received_message = resource_service.get_resource_string_by_name("received_for_should_run_daemon_guard_message_template").unwrap();
+                should_run_daemon = *temp_should_run_daemon_guard;
+                let received_message = resource_service.get_resource_string_by_name("received_for_should_run_daemon_guard_message_template");
                 log::debug!("{}{}", received_message, should_run_daemon);
             }
         }
-        let daemon_killed_message = resource_service.get_resource_string_by_name("daemon_killed_message").unwrap();
+        let daemon_killed_message = resource_service.get_resource_string_by_name("daemon_killed_message");
         log::debug!("{}", daemon_killed_message);
     }
+
+    fn start_front_job(pump_queue: &mut VecDeque<PumpJob>) -> Option<PumpJob> {
+        let pump_job = pump_queue.front_mut()?;
+        pump_job.started_at = Some(Instant::now());
+        Some(*pump_job)
+    }
+
+    fn update_running_job(pump_queue_arc: &Mutex<VecDeque<PumpJob>>, pump_job_id: Uuid, update: impl FnOnce(&mut PumpJob)) {
+        if let Ok(mut pump_queue) = pump_queue_arc.lock() {
+            if let Some(running_pump_job) = pump_queue.iter_mut().find(|pump_job| pump_job.id == pump_job_id) {
+                update(running_pump_job);
+            }
+        }
+    }
+
+    // Waits for the duration unless the daemon is killed, telling whether it waited the whole time
+    fn wait_for_daemon(should_run_daemon_pair: &(Mutex<bool>, Condvar), duration: Duration) -> bool {
+        let (should_run_daemon_mutex, cvar) = should_run_daemon_pair;
+        match should_run_daemon_mutex.lock() {
+            Ok(should_run_daemon_guard) => cvar.wait_timeout_while(should_run_daemon_guard, duration, |should_run_daemon| *should_run_daemon)
+                .is_ok_and(|(_, wait_timeout_result)| wait_timeout_result.timed_out()),
+            Err(_) => false
+        }
+    }
+
+    // Runs the pump for the job's duration unless the job is stopped or the daemon killed in the meantime, telling whether it ran in full
+    fn wait_for_pump_job(should_run_daemon_pair: &(Mutex<bool>, Condvar), pump_queue_arc: &Mutex<VecDeque<PumpJob>>, pump_job_id: Uuid, duration: Duration) -> bool {
+        let (should_run_daemon_mutex, cvar) = should_run_daemon_pair;
+        match should_run_daemon_mutex.lock() {
+            Ok(should_run_daemon_guard) => cvar.wait_timeout_while(should_run_daemon_guard, duration, |should_run_daemon| {
+                *should_run_daemon && pump_queue_arc.lock().is_ok_and(|pump_queue| pump_queue.iter().any(|pump_job| pump_job.id == pump_job_id))
+            }).is_ok_and(|(_, wait_timeout_result)| wait_timeout_result.timed_out()),
+            Err(_) => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use std::time::Instant;
+    use uuid::Uuid;
+    use crate::api::models::{ ApiErrorCode, JobPriority, PumpJob, PumpJobKind, QueuePolicy };
+    use super::{ PumpGroup, PumpService, MAX_PUMP_GROUP_RECORDS };
+
+    const FAIR: QueuePolicy = QueuePolicy { is_fair_queueing_enabled: true, max_pending_groups_per_requester: None };
+
+    fn create_pump_job(group_id: Uuid, requester: Option<Uuid>, priority: JobPriority) -> PumpJob {
+        PumpJob {
+            id: Uuid::new_v4(),
+            pump_number: 1,
+            duration_in_milliseconds: 1000,
+            ml: 10.0,
+            kind: PumpJobKind::Pour,
+            group_id,
+            cup_id: None,
+            priority,
+            requester,
+            started_at: None,
+            pumped_milliseconds: 0,
+            segment_started_at: None
+        }
+    }
+
+    // Queues a group of two jobs where the policy puts it, returning its id
+    fn enqueue(queue_policy: &QueuePolicy, pump_queue: &mut VecDeque<PumpJob>, requester: Option<Uuid>, priority: JobPriority) -> Uuid {
+        let group_id = Uuid::new_v4();
+        let index = PumpService::get_insert_index(queue_policy, pump_queue, priority, requester).0;
+        pump_queue.insert(index, create_pump_job(group_id, requester, priority));
+        pump_queue.insert(index + 1, create_pump_job(group_id, requester, priority));
+        group_id
+    }
+
+    fn get_group_order(pump_queue: &VecDeque<PumpJob>) -> Vec<Uuid> {
+        let mut group_ids: Vec<Uuid> = vec![];
+        for pump_job in pump_queue {
+            if group_ids.last() != Some(&pump_job.group_id) {
+                group_ids.push(pump_job.group_id);
+            }
+        }
+        group_ids
+    }
+
+    #[test]
+    fn interleaves_requesters_with_uneven_backlogs() {
+        let (a, b, c) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let mut pump_queue = VecDeque::new();
+        let a1 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        let a2 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        let a3 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        let b1 = enqueue(&FAIR, &mut pump_queue, b, JobPriority::Normal);
+        let b2 = enqueue(&FAIR, &mut pump_queue, b, JobPriority::Normal);
+        let c1 = enqueue(&FAIR, &mut pump_queue, c, JobPriority::Normal);
+        let b3 = enqueue(&FAIR, &mut pump_queue, b, JobPriority::Normal);
+        assert_eq!(get_group_order(&pump_queue), vec![a1, b1, c1, a2, b2, a3, b3]);
+    }
+
+    #[test]
+    fn treats_jobs_without_requester_as_one_requester() {
+        let a = Some(Uuid::new_v4());
+        let mut pump_queue = VecDeque::new();
+        let a1 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        let a2 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        let none1 = enqueue(&FAIR, &mut pump_queue, None, JobPriority::Normal);
+        let none2 = enqueue(&FAIR, &mut pump_queue, None, JobPriority::Normal);
+        let none3 = enqueue(&FAIR, &mut pump_queue, None, JobPriority::Normal);
+        assert_eq!(get_group_order(&pump_queue), vec![a1, none1, a2, none2, none3]);
+    }
+
+    #[test]
+    fn keeps_rounds_within_each_priority() {
+        let (a, b) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let mut pump_queue = VecDeque::new();
+        let a1 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        let a2 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        let a_high = enqueue(&FAIR, &mut pump_queue, a, JobPriority::High);
+        let b_low = enqueue(&FAIR, &mut pump_queue, b, JobPriority::Low);
+        let b1 = enqueue(&FAIR, &mut pump_queue, b, JobPriority::Normal);
+        assert_eq!(get_group_order(&pump_queue), vec![a_high, a1, b1, a2, b_low]);
+    }
+
+    #[test]
+    fn queues_first_come_first_served_without_fair_queueing() {
+        let queue_policy = QueuePolicy { is_fair_queueing_enabled: false, max_pending_groups_per_requester: None };
+        let (a, b) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let mut pump_queue = VecDeque::new();
+        let a1 = enqueue(&queue_policy, &mut pump_queue, a, JobPriority::Normal);
+        let a2 = enqueue(&queue_policy, &mut pump_queue, a, JobPriority::Normal);
+        let b1 = enqueue(&queue_policy, &mut pump_queue, b, JobPriority::Normal);
+        assert_eq!(get_group_order(&pump_queue), vec![a1, a2, b1]);
+    }
+
+    #[test]
+    fn never_inserts_into_running_group() {
+        let (a, b) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let mut pump_queue = VecDeque::new();
+        let a1 = enqueue(&FAIR, &mut pump_queue, a, JobPriority::Normal);
+        pump_queue[0].started_at = Some(Instant::now());
+        let b_urgent = enqueue(&FAIR, &mut pump_queue, b, JobPriority::Urgent);
+        assert_eq!(get_group_order(&pump_queue), vec![a1, b_urgent]);
+        assert_eq!(pump_queue[2].group_id, b_urgent);
+    }
+
+    #[test]
+    fn limits_pending_groups_per_requester() {
+        let queue_policy = QueuePolicy { is_fair_queueing_enabled: true, max_pending_groups_per_requester: Some(2) };
+        let (a, b) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let mut pump_queue = VecDeque::new();
+        enqueue(&queue_policy, &mut pump_queue, a, JobPriority::Normal);
+        assert!(PumpService::check_pending_group_limit(&queue_policy, &pump_queue, a).is_ok());
+        enqueue(&queue_policy, &mut pump_queue, a, JobPriority::Normal);
+        let error = PumpService::check_pending_group_limit(&queue_policy, &pump_queue, a).unwrap_err();
+        assert_eq!(error.code, ApiErrorCode::PendingOrderLimitReached);
+        assert!(PumpService::check_pending_group_limit(&queue_policy, &pump_queue, b).is_ok());
+        for _ in 0..3 {
+            enqueue(&queue_policy, &mut pump_queue, None, JobPriority::Normal);
+        }
+        assert!(PumpService::check_pending_group_limit(&queue_policy, &pump_queue, None).is_ok());
+    }
+
+    fn create_pump_group(id: Uuid) -> PumpGroup {
+        PumpGroup { id, cup_capacity: None, priority: JobPriority::Normal, requester: None, kind: PumpJobKind::Pour }
+    }
+
+    fn create_group_pump_job(group_id: Uuid, pump_number: u8, ml: f32) -> PumpJob {
+        PumpJob { pump_number, ml, ..create_pump_job(group_id, None, JobPriority::Normal) }
+    }
+
+    #[test]
+    fn keeps_pumped_ml_after_group_leaves_queue() {
+        let pump_group_records = Mutex::new(VecDeque::new());
+        let group_id = Uuid::new_v4();
+        PumpService::record_queued_jobs(&pump_group_records, &[(1, 10.0), (2, 20.0)], &create_pump_group(group_id));
+        PumpService::record_finished_job(&pump_group_records, &create_group_pump_job(group_id, 1, 10.0), 10.0, true);
+        let pump_group_record = pump_group_records.lock().unwrap()[0].clone();
+        assert!(!pump_group_record.is_finished());
+        PumpService::record_finished_job(&pump_group_records, &create_group_pump_job(group_id, 2, 20.0), 20.0, true);
+        let pump_group_record = pump_group_records.lock().unwrap()[0].clone();
+        assert!(pump_group_record.is_finished());
+        assert!(!pump_group_record.is_dropped);
+        assert_eq!(pump_group_record.pumped_ml, 30.0);
+        assert_eq!(pump_group_record.completed_pump_numbers, vec![1, 2]);
+    }
+
+    #[test]
+    fn waits_for_running_job_when_dropping_group() {
+        let pump_group_records = Mutex::new(VecDeque::new());
+        let group_id = Uuid::new_v4();
+        PumpService::record_queued_jobs(&pump_group_records, &[(1, 10.0), (2, 20.0)], &create_pump_group(group_id));
+        let mut running_pump_job = create_group_pump_job(group_id, 1, 10.0);
+        running_pump_job.started_at = Some(Instant::now());
+        PumpService::record_dropped_jobs(&pump_group_records, &[running_pump_job, create_group_pump_job(group_id, 2, 20.0)]);
+        let pump_group_record = pump_group_records.lock().unwrap()[0].clone();
+        assert!(pump_group_record.is_dropped);
+        assert!(!pump_group_record.is_finished());
+        PumpService::record_finished_job(&pump_group_records, &running_pump_job, 4.0, false);
+        let pump_group_record = pump_group_records.lock().unwrap()[0].clone();
+        assert!(pump_group_record.is_finished());
+        assert_eq!(pump_group_record.pumped_ml, 4.0);
+        assert!(pump_group_record.completed_pump_numbers.is_empty());
+    }
+
+    #[test]
+    fn adds_top_ups_to_existing_record() {
+        let pump_group_records = Mutex::new(VecDeque::new());
+        let group_id = Uuid::new_v4();
+        PumpService::record_queued_jobs(&pump_group_records, &[(1, 10.0)], &create_pump_group(group_id));
+        PumpService::record_finished_job(&pump_group_records, &create_group_pump_job(group_id, 1, 10.0), 10.0, true);
+        PumpService::record_queued_jobs(&pump_group_records, &[(2, 5.0)], &create_pump_group(group_id));
+        let pump_group_records = pump_group_records.lock().unwrap();
+        assert_eq!(pump_group_records.len(), 1);
+        assert_eq!(pump_group_records[0].planned_ml, 15.0);
+        assert_eq!(pump_group_records[0].pumped_ml, 10.0);
+        assert!(!pump_group_records[0].is_finished());
+    }
+
+    #[test]
+    fn forgets_only_finished_groups_beyond_limit() {
+        let pump_group_records = Mutex::new(VecDeque::new());
+        let unfinished_group_id = Uuid::new_v4();
+        PumpService::record_queued_jobs(&pump_group_records, &[(1, 10.0)], &create_pump_group(unfinished_group_id));
+        let finished_group_id = Uuid::new_v4();
+        PumpService::record_queued_jobs(&pump_group_records, &[(1, 10.0)], &create_pump_group(finished_group_id));
+        PumpService::record_finished_job(&pump_group_records, &create_group_pump_job(finished_group_id, 1, 10.0), 10.0, true);
+        for _ in 2..=MAX_PUMP_GROUP_RECORDS {
+            PumpService::record_queued_jobs(&pump_group_records, &[(1, 10.0)], &create_pump_group(Uuid::new_v4()));
+        }
+        let pump_group_records = pump_group_records.lock().unwrap();
+        assert_eq!(pump_group_records.len(), MAX_PUMP_GROUP_RECORDS);
+        assert_eq!(pump_group_records[0].id, unfinished_group_id);
+        assert!(pump_group_records.iter().all(|pump_group_record| pump_group_record.id != finished_group_id));
+    }
 }
@@ -1,64 +1,82 @@
-use std::collections::VecDeque;
-use std::sync::{ Arc, Mutex, Condvar };
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::Arc;
 use serde_json::json;
 #[cfg(feature = "use-gpio")]
 use gpio_cdev::{ Chip, LineRequestFlags, LineHandle };
 #[cfg(not(feature = "use-gpio"))]
 use crate::api::mock::{ Chip, LineRequestFlags, LineHandle };
-use crate::api::models::PumpState;
-use crate::api::{ ResourceService, PumpService };
+use crate::api::models::{ PumpState, Config, PumpHeat, PumpRecord };
+use crate::api::{ ResourceService, PumpDriver, PumpService };
 
 pub struct PumpServiceFactory {}
 
 impl PumpServiceFactory {
-    pub fn create_or_panic(resource_service: Arc<ResourceService>) -> PumpService {
-        let is_relay_inverted = dotenv::var("IS_RELAY_INVERTED").unwrap().ends_with('1');
-        let ms_per_ml = dotenv::var("MILLISECONDS_PER_ML").unwrap().parse::<u64>().unwrap();
-        let rpi_chip_name = dotenv::var("RPI_CHIP_NAME").unwrap();
-        let pump_pin_numbers_string = dotenv::var("ORDERED_PUMP_PIN_NUMBERS").unwrap();
-        let pump_pin_numbers: Vec<u32> = pump_pin_numbers_string.split(',').map(|num| num.parse::<u32>().unwrap()).collect();
-        let line_handles = Self::get_line_handles(resource_service.as_ref(), rpi_chip_name, &pump_pin_numbers, is_relay_inverted);
-        let initial_pump_states = (1..=pump_pin_numbers.len() as u8).map(|pump_number| PumpState { pump_number, is_running: is_relay_inverted }).collect();
+    pub fn create(resource_service: Arc<ResourceService>, config: &Config, pump_drivers: Vec<PumpDriver>) -> Result<PumpService, String> {
+        let is_relay_inverted = config.is_relay_inverted;
+        let file_path = config.pumps_file_path.clone();
+        let mut pump_records: BTreeMap<u8, PumpRecord> = match fs::read_to_string(&file_path) {
+            Ok(pump_records_json) => serde_json::from_str(&pump_records_json)
+                .map_err(|error| format!("Couldn't parse {}: {}", file_path.display(), error))?,
+            // Nothing has been recorded yet
+            Err(error) if error.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(format!("Couldn't read {}: {}", file_path.display(), error))
+        };
+        let initial_pump_states = (1..=config.get_number_of_pumps())
+            .map(|pump_number| PumpState {
+                pump_number,
+                is_running: is_relay_inverted,
+                is_cleaning_due: false,
+                is_cooling_down: false,
+                heat: PumpHeat::default(),
+                record: pump_records.remove(&pump_number).unwrap_or_default()
+            })
+            .collect();
 
-        PumpService::new(
-            resource_service,
-            is_relay_inverted,
-            pump_pin_numbers,
-            ms_per_ml,
-            None,
-            Arc::new(Mutex::new(line_handles)), // Revise all 3 of these with RwLock where appropriate
-            Arc::new(Mutex::new(initial_pump_states)),
-            Arc::new(Mutex::new(VecDeque::new())),
-            Arc::new((Mutex::new(true), Condvar::new()))
-        )
+        Ok(PumpService::new(resource_service, config, pump_drivers, initial_pump_states))
     }
 
-    fn get_line_handles(resource_service: &ResourceService, rpi_chip_name: String, pump_pin_numbers: &Vec<u32>, is_relay_inverted: bool) -> Vec<LineHandle> {
+    pub fn open_chip(resource_service: &ResourceService, config: &Config) -> Result<Chip, String> {
         if cfg!(not(feature = "use-gpio")) {
-            let mocking_gpio_message = resource_service.get_resource_string_by_name("mocking_gpio_info_message").unwrap();
+            let mocking_gpio_message = resource_service.get_resource_string_by_name("mocking_gpio_info_message");
             log::info!("{}", mocking_gpio_message);
         }
 
-        let getting_chip_message_data = &json!({ "chip_name": rpi_chip_name });
-        let getting_chip_message = resource_service.render_resource_template_string_by_name("getting_chip_info_message_template", getting_chip_message_data).unwrap();
+        let getting_chip_message_data = &json!({ "chip_name": config.rpi_chip_name });
+        let getting_chip_message = resource_service.render_resource_template_string_by_name("getting_chip_info_message_template", getting_chip_message_data);
         log::info!("{}", getting_chip_message);
-        let mut chip = Chip::new(&rpi_chip_name).unwrap();
+        Chip::new(&config.rpi_chip_name).map_err(|error| format!("Couldn't open chip \"{}\": {}", config.rpi_chip_name, error))
+    }
+
+    pub fn request_pump_drivers(resource_service: &ResourceService, config: &Config, chip: &mut Chip) -> Result<Vec<PumpDriver>, String> {
         let mut default_state: u8 = 0;
-        if is_relay_inverted {
+        if config.is_relay_inverted {
             default_state = 1;
         }
 
-        let mut line_handles: Vec<LineHandle> = vec![];
-        let mut pump_number = 1;
-        for pin_number in pump_pin_numbers {
-            let getting_line_handle_message_data = &json!({"pump_number": pump_number, "pin_number": pin_number });
-            let getting_line_handle_message = resource_service.render_resource_template_string_by_name("getting_line_handle_info_message_template", getting_line_handle_message_data).unwrap();
-            log::info!("{}", getting_line_handle_message);
-            let line = chip.get_line(*pin_number).unwrap();
-            line_handles.push(line.request(LineRequestFlags::OUTPUT, default_state, format!("Pump {}", pump_number).as_str()).unwrap());
-            pump_number += 1;
+        let mut pump_drivers: Vec<PumpDriver> = vec![];
+        for (index, pin_number) in config.ordered_pump_pin_numbers.iter().enumerate() {
+            let pump_number = index + 1;
+            let motor_line_handle = Self::request_line_handle(resource_service, chip, pump_number, *pin_number, default_state, &format!("Pump {}", pump_number))?;
+            // Starts out facing forwards
+            let direction_line_handle = match config.direction_pin_numbers[index] {
+                Some(direction_pin_number) => Some(Self::request_line_handle(resource_service, chip, pump_number, direction_pin_number, 0, &format!("Pump {} direction", pump_number))?),
+                None => None
+            };
+            pump_drivers.push(PumpDriver::new(motor_line_handle, direction_line_handle, config.is_relay_inverted));
         }
 
-        line_handles
+        Ok(pump_drivers)
+    }
+
+    fn request_line_handle(resource_service: &ResourceService, chip: &mut Chip, pump_number: usize, pin_number: u32, default_state: u8, consumer: &str) -> Result<LineHandle, String> {
+        let getting_line_handle_message_data = &json!({"pump_number": pump_number, "pin_number": pin_number });
+        let getting_line_handle_message = resource_service.render_resource_template_string_by_name("getting_line_handle_info_message_template", getting_line_handle_message_data);
+        log::info!("{}", getting_line_handle_message);
+        let line = chip.get_line(pin_number)
+            .map_err(|error| format!("Couldn't get line for pump {} on pin {}: {}", pump_number, pin_number, error))?;
+        line.request(LineRequestFlags::OUTPUT, default_state, consumer)
+            .map_err(|error| format!("Couldn't request line for pump {} on pin {}: {}", pump_number, pin_number, error))
     }
 }
@@ -0,0 +1,44 @@
+use rocket::Request;
+use rocket::request::{ FromRequest, Outcome };
+use serde_json::json;
+use uuid::Uuid;
+use crate::api::models::{ ApiError, ApiErrorCode, CupCapacity };
+#[cfg(feature = "bff")]
+use std::sync::Arc;
+#[cfg(feature = "bff")]
+use crate::api::DrinkService;
+
+// The cup a raw pour goes into, given as ?cup=<id>. Cups live in the settings so without them no cup can be found
+pub struct RequestedCup(pub Result<Option<CupCapacity>, ApiError>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestedCup {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let cup = match request.query_value::<&str>("cup") {
+            Some(Ok(cup)) => cup,
+            _ => return Outcome::Success(RequestedCup(Ok(None)))
+        };
+        let cup_not_found = ApiError::with_details(ApiErrorCode::CupNotFound, json!({ "cup_id": cup }));
+        let cup_id = match Uuid::parse_str(cup) {
+            Ok(cup_id) => cup_id,
+            Err(_) => return Outcome::Success(RequestedCup(Err(cup_not_found)))
+        };
+        let cup_capacity = match find_cup_capacity(request, cup_id) {
+            Some(cup_capacity) => cup_capacity.map(Some),
+            None => Err(cup_not_found)
+        };
+        Outcome::Success(RequestedCup(cup_capacity))
+    }
+}
+
+#[cfg(feature = "bff")]
+fn find_cup_capacity(request: &Request<'_>, cup_id: Uuid) -> Option<Result<CupCapacity, ApiError>> {
+    request.rocket().state::<Arc<DrinkService>>().map(|drink_service| drink_service.find_cup_capacity(cup_id))
+}
+
+#[cfg(not(feature = "bff"))]
+fn find_cup_capacity(_request: &Request<'_>, _cup_id: Uuid) -> Option<Result<CupCapacity, ApiError>> {
+    None
+}
@@ -0,0 +1,10 @@
+use serde_json::Value;
+
+// A locale's strings looked up by name, whatever format they were written in
+pub trait ResourceCatalog: Send + Sync {
+    fn contains(&self, name: &str) -> bool;
+
+    fn get_string(&self, name: &str) -> Option<String>;
+
+    fn render(&self, name: &str, data: &Value) -> Option<String>;
+}
@@ -1,34 +1,147 @@
-use handlebars::Handlebars;
+use std::collections::HashMap;
 use serde::Serialize;
-use crate::api::models::resources_xml::ResourcesElement;
+use serde_json::Value;
+use crate::api::ResourceCatalog;
+
+// Every resource the code looks up; checked on startup so a missing or broken string is caught before it's needed
+pub const REQUIRED_RESOURCE_NAMES: &[&str] = &[
+    "invalid_pump_number_error_message",
+    "mocking_gpio_info_message",
+    "getting_chip_info_message_template",
+    "getting_line_handle_info_message_template",
+    "scheduling_pump_info_message_template",
+    "processing_job_info_message_template",
+    "cooling_down_pump_info_message_template",
+    "retracting_pump_info_message_template",
+    "setting_pump_high_info_message_template",
+    "setting_pump_low_info_message_template",
+    "finished_processing_queue_info_message",
+    "expected_ml_to_pump_error_message",
+    "ml_to_pump_parse_error_message",
+    "invalid_ml_to_pump_error_message",
+    "pump_jobs_missing_error_message",
+    "pump_job_not_found_error_message_template",
+    "pump_job_running_error_message_template",
+    "pump_group_cup_mismatch_error_message_template",
+    "priority_invalid_error_message_template",
+    "pour_amount_invalid_error_message",
+    "unit_of_measure_unknown_error_message_template",
+    "invalid_settings_error_message",
+    "truncating_settings_file_error_message",
+    "write_to_settings_file_error_message_template",
+    "create_or_open_settings_file_error_message_template",
+    "create_settings_directory_error_message_template",
+    "settings_serialization_error_message_template",
+    "daemon_thread_started_message",
+    "daemon_thread_killed_message",
+    "starting_daemon_thread_message",
+    "daemon_killed_while_processing_message",
+    "waiting_for_should_run_daemon_guard_message",
+    "received_for_should_run_daemon_guard_message_template",
+    "daemon_killed_message",
+    "failed_to_lock_pump_states_error_message",
+    "daemon_not_running_error_message",
+    "route_not_found_error_message",
+    "malformed_request_error_message",
+    "internal_error_message",
+    "authentication_required_error_message",
+    "insufficient_role_error_message_template",
+    "api_key_not_found_error_message_template",
+    "api_key_name_missing_error_message",
+    "api_keys_not_saved_error_message_template",
+    "drink_not_found_error_message_template",
+    "cup_not_found_error_message_template",
+    "cup_not_selected_error_message",
+    "drink_has_no_ingredients_error_message",
+    "ingredient_not_on_pump_error_message_template",
+    "guest_not_found_error_message_template",
+    "guest_name_missing_error_message",
+    "guest_profile_invalid_error_message",
+    "consumption_limit_reached_error_message_template",
+    "pending_order_limit_reached_error_message_template",
+    "guests_not_saved_error_message_template",
+    "schedule_not_found_error_message_template",
+    "schedule_invalid_error_message_template",
+    "schedules_not_saved_error_message_template",
+    "pump_queue_not_empty_error_message_template",
+    "pumps_being_cleaned_error_message",
+    "maintenance_task_not_found_error_message_template",
+    "replace_tubing_reminder_message_template",
+    "replace_pump_reminder_message_template",
+    "cleaning_program_not_found_error_message_template",
+    "cleaning_run_not_found_error_message",
+    "cleaning_run_active_error_message_template",
+    "cleaning_run_not_awaiting_confirmation_error_message",
+    "pour_modifiers_invalid_error_message_template",
+    "cup_would_overflow_error_message_template"
+];
 
-#[derive(Clone)]
 pub struct ResourceService {
-    resource_element: ResourcesElement
+    catalogs: HashMap<String, Box<dyn ResourceCatalog>>,
+    fallback_catalog: Box<dyn ResourceCatalog>,
+    default_locale: String,
+    log_locale: String
 }
 
-impl ResourceService{
-    pub fn new(resource_element: ResourcesElement) -> ResourceService {
+impl ResourceService {
+    pub fn new(catalogs: HashMap<String, Box<dyn ResourceCatalog>>, fallback_catalog: Box<dyn ResourceCatalog>, default_locale: String, log_locale: String) -> ResourceService {
         ResourceService {
-            resource_element
+            catalogs,
+            fallback_catalog,
+            default_locale,
+            log_locale
         }
     }
 
-    pub fn get_resource_string_by_name(&self, name: &str) -> Option<String> {
-        for string_element in &self.resource_element.strings {
-            if string_element.name == name {
-                return Some(string_element.content.clone())
-            }
+    pub fn get_locales(&self) -> Vec<String> {
+        let mut locales: Vec<String> = self.catalogs.keys().cloned().collect();
+        locales.sort();
+        locales
+    }
+
+    pub fn contains_resource_name(&self, name: &str) -> bool {
+        self.find_catalog(&[], name).is_some()
+    }
+
+    // Strings for the server log use the configured log locale
+    pub fn get_resource_string_by_name(&self, name: &str) -> String {
+        self.get_localized_resource_string_by_name(std::slice::from_ref(&self.log_locale), name)
+    }
+
+    pub fn render_resource_template_string_by_name<T: Serialize>(&self, name: &str, data: &T) -> String {
+        self.render_localized_resource_template_string_by_name(std::slice::from_ref(&self.log_locale), name, data)
+    }
+
+    pub fn get_localized_resource_string_by_name(&self, locales: &[String], name: &str) -> String {
+        match self.find_catalog(locales, name).and_then(|catalog| catalog.get_string(name)) {
+            Some(content) => content,
+            // Startup already reported it; the name still tells the reader what was meant
+            None => name.to_string()
         }
-        None
     }
 
-    pub fn render_resource_template_string_by_name<T: Serialize>(&self, name: &str, data: &T) -> Option<String> {
-        let handlebars = Handlebars::new();
-        let template_string = self.get_resource_string_by_name(name)?;
-        match handlebars.render_template(template_string.as_str(), data) {
-            Ok(rendered) => Some(rendered),
-            Err(_) => None
+    pub fn render_localized_resource_template_string_by_name<T: Serialize>(&self, locales: &[String], name: &str, data: &T) -> String {
+        let data = serde_json::to_value(data).unwrap_or(Value::Null);
+        match self.find_catalog(locales, name).and_then(|catalog| catalog.render(name, &data)) {
+            Some(rendered) => rendered,
+            None => self.get_localized_resource_string_by_name(locales, name)
+        }
+    }
+
+    // Tries each locale, then its language without the region (de-at -> de), then the default locale and finally the compiled-in strings
+    fn find_catalog(&self, locales: &[String], name: &str) -> Option<&dyn ResourceCatalog> {
+        let mut fallback_chain: Vec<&str> = vec![];
+        for locale in locales {
+            fallback_chain.push(locale);
+            if let Some((language, _)) = locale.split_once('-') {
+                fallback_chain.push(language);
+            }
         }
+        fallback_chain.push(&self.default_locale);
+        fallback_chain.into_iter()
+            .filter_map(|locale| self.catalogs.get(locale))
+            .map(|catalog| catalog.as_ref())
+            .chain(std::iter::once(self.fallback_catalog.as_ref()))
+            .find(|catalog| catalog.contains(name))
     }
 }
@@ -1,17 +1,111 @@
+use std::collections::HashMap;
 use std::fs;
+use std::path::{ Path, PathBuf };
 use hard_xml::XmlRead;
+use crate::api::models::{ AcceptedLocales, Config };
 use crate::api::models::resources_xml::ResourcesElement;
-use crate::api::ResourceService;
+use crate::api::{ FluentResourceCatalog, ResourceCatalog, ResourceService, XmlResourceCatalog, REQUIRED_RESOURCE_NAMES };
+
+// Compiled in so a missing or partial strings file still leaves every message available
+const DEFAULT_STRINGS_XML: &str = include_str!("../../resources/strings.xml");
+const DEFAULT_LOCALE: &str = "en";
+const FLUENT_EXTENSION: &str = "ftl";
+const XML_EXTENSION: &str = "xml";
 
 pub struct ResourceServiceFactory {}
 
 impl ResourceServiceFactory {
-    pub fn create_or_panic() -> ResourceService {
-        let home_dir = dirs::home_dir().unwrap();
-        let strings_xml_file_path = dotenv::var("STRINGS_XML_FILE_PATH").unwrap();
-        let file_path = home_dir.join(strings_xml_file_path);
-        let resource_xml_content = fs::read_to_string(file_path).unwrap();
-        let resource_element = ResourcesElement::from_str(&resource_xml_content).unwrap();
-        ResourceService::new(resource_element)
+    // Returns the service along with any problems found while loading the strings
+    pub fn create(config: Option<&Config>) -> (ResourceService, Vec<String>) {
+        let mut warnings = vec![];
+        let default_locale = config.map_or(DEFAULT_LOCALE.to_string(), |config| config.default_locale.clone());
+        let log_locale = config.map_or(DEFAULT_LOCALE.to_string(), |config| config.log_locale.clone());
+
+        let fallback_catalog = match ResourcesElement::from_str(DEFAULT_STRINGS_XML) {
+            Ok(default_resource_element) => {
+                let (catalog, errors) = XmlResourceCatalog::new(default_resource_element);
+                warnings.extend(errors.into_iter().map(|error| format!("{} in compiled-in defaults", error)));
+                catalog
+            },
+            Err(error) => {
+                warnings.push(format!("Couldn't parse compiled-in default strings: {}", error));
+                XmlResourceCatalog::new(ResourcesElement { strings: vec![] }).0
+            }
+        };
+        let mut catalogs: HashMap<String, Box<dyn ResourceCatalog>> = HashMap::new();
+        if let Some(config) = config {
+            // Either a strings.xml or a strings.ftl file makes up the default locale
+            let file_path = &config.strings_xml_file_path;
+            match Self::load_catalog(&default_locale, file_path) {
+                Ok((catalog, errors)) => {
+                    warnings.extend(errors);
+                    catalogs.insert(default_locale.clone(), catalog);
+                },
+                Err(error) => warnings.push(format!("{}, using compiled-in defaults", error))
+            }
+            // Other locales sit next to it, e.g. strings.de.xml or strings.de.ftl
+            for (locale, locale_file_path) in Self::find_locale_files(file_path) {
+                match Self::load_catalog(&locale, &locale_file_path) {
+                    Ok((catalog, errors)) => {
+                        warnings.extend(errors);
+                        catalogs.insert(locale, catalog);
+                    },
+                    Err(error) => warnings.push(error)
+                }
+            }
+        }
+
+        let resource_service = ResourceService::new(catalogs, Box::new(fallback_catalog), default_locale.clone(), log_locale.clone());
+        for resource_name in REQUIRED_RESOURCE_NAMES {
+            if !resource_service.contains_resource_name(resource_name) {
+                warnings.push(format!("Missing string \"{}\"", resource_name));
+            }
+        }
+        if log_locale != default_locale && !resource_service.get_locales().contains(&log_locale) {
+            warnings.push(format!("No strings for log locale \"{}\", logging in \"{}\"", log_locale, default_locale));
+        }
+
+        (resource_service, warnings)
+    }
+
+    fn load_catalog(locale: &str, file_path: &Path) -> Result<(Box<dyn ResourceCatalog>, Vec<String>), String> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|error| format!("Couldn't read {}: {}", file_path.display(), error))?;
+        let file_name = file_path.display().to_string();
+        if file_path.extension().and_then(|extension| extension.to_str()) == Some(FLUENT_EXTENSION) {
+            let (catalog, errors) = FluentResourceCatalog::new(locale, content);
+            let warnings = errors.into_iter().map(|error| format!("{} in {}", error, file_name)).collect();
+            return Ok((Box::new(catalog), warnings));
+        }
+        let resource_element = ResourcesElement::from_str(&content)
+            .map_err(|error| format!("Couldn't parse {}: {}", file_name, error))?;
+        let (catalog, errors) = XmlResourceCatalog::new(resource_element);
+        let warnings = errors.into_iter().map(|error| format!("{} in {}", error, file_name)).collect();
+        Ok((Box::new(catalog), warnings))
+    }
+
+    fn find_locale_files(strings_file_path: &Path) -> Vec<(String, PathBuf)> {
+        let (directory, file_stem) = match (strings_file_path.parent(), strings_file_path.file_stem().and_then(|stem| stem.to_str())) {
+            (Some(directory), Some(file_stem)) => (directory, file_stem),
+            _ => return vec![]
+        };
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(_) => return vec![]
+        };
+        let prefix = format!("{}.", file_stem);
+        let mut locale_files: Vec<(String, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                let (locale, extension) = file_name.strip_prefix(&prefix)?.rsplit_once('.')?;
+                if locale.is_empty() || (extension != XML_EXTENSION && extension != FLUENT_EXTENSION) {
+                    return None;
+                }
+                Some((AcceptedLocales::normalize(locale), entry.path()))
+            })
+            .collect();
+        locale_files.sort();
+        locale_files
     }
 }
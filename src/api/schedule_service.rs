@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{ Arc, Condvar, Mutex };
+use std::thread;
+use std::time::Duration;
+use chrono::{ DateTime, TimeDelta, Utc };
+use serde_json::json;
+use uuid::Uuid;
+use crate::api::models::{ ApiError, ApiErrorCode, Caller, CronExpression, NewPumpJob, NewSchedule, Schedule, ScheduleRun, ScheduledAction, UnitsOfMeasure };
+#[cfg(feature = "bff")]
+use crate::api::OrderService;
+use crate::api::PumpService;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ScheduleService {
+    schedules: Mutex<Vec<Schedule>>,
+    schedules_file_path: PathBuf,
+    pump_service: Arc<Mutex<PumpService>>,
+    units_of_measure: UnitsOfMeasure,
+    // Only there when orders are being taken
+    #[cfg(feature = "bff")]
+    order_service: Option<Arc<OrderService>>,
+    daemon_thread: Mutex<Option<thread::JoinHandle<()>>>,
+    run_daemon_pair: (Mutex<bool>, Condvar)
+}
+
+impl ScheduleService {
+    pub fn new(
+        schedules: Vec<Schedule>,
+        schedules_file_path: PathBuf,
+        pump_service: Arc<Mutex<PumpService>>,
+        units_of_measure: UnitsOfMeasure,
+        #[cfg(feature = "bff")]
+        order_service: Option<Arc<OrderService>>
+    ) -> ScheduleService {
+        ScheduleService {
+            schedules: Mutex::new(schedules),
+            schedules_file_path,
+            pump_service,
+            units_of_measure,
+            #[cfg(feature = "bff")]
+            order_service,
+            daemon_thread: Mutex::new(None),
+            run_daemon_pair: (Mutex::new(true), Condvar::new())
+        }
+    }
+
+    pub fn get_schedules(&self) -> Vec<Schedule> {
+        self.schedules.lock().unwrap().clone()
+    }
+
+    pub fn create_schedule(&self, caller: &Caller, new_schedule: NewSchedule) -> Result<Schedule, ApiError> {
+        let schedule = self.create_schedule_entry(Uuid::new_v4(), caller, new_schedule, None)?;
+        let mut schedules = self.schedules.lock().unwrap();
+        schedules.push(schedule.clone());
+        self.save(&schedules)?;
+        Ok(schedule)
+    }
+
+    // The schedule runs as whoever edited it last
+    pub fn update_schedule(&self, caller: &Caller, id: Uuid, new_schedule: NewSchedule) -> Result<Schedule, ApiError> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let existing_schedule = schedules.iter_mut().find(|schedule| schedule.id == id)
+            .ok_or_else(|| Self::create_schedule_not_found_error(id))?;
+        let schedule = self.create_schedule_entry(id, caller, new_schedule, existing_schedule.last_run.clone())?;
+        *existing_schedule = schedule.clone();
+        self.save(&schedules)?;
+        Ok(schedule)
+    }
+
+    pub fn delete_schedule(&self, id: Uuid) -> Result<(), ApiError> {
+        let mut schedules = self.schedules.lock().unwrap();
+        if !schedules.iter().any(|schedule| schedule.id == id) {
+            return Err(Self::create_schedule_not_found_error(id));
+        }
+        schedules.retain(|schedule| schedule.id != id);
+        self.save(&schedules)
+    }
+
+    fn create_schedule_entry(&self, id: Uuid, caller: &Caller, new_schedule: NewSchedule, last_run: Option<ScheduleRun>) -> Result<Schedule, ApiError> {
+        match &new_schedule.action {
+            ScheduledAction::PumpJobs { jobs, .. } => {
+                let pump_amounts = self.get_pump_amounts(jobs)?;
+                self.pump_service.lock().unwrap().validate_pump_amounts(&pump_amounts)?;
+            },
+            #[cfg(feature = "bff")]
+            ScheduledAction::Order { .. } => {
+                self.get_order_service()?;
+            }
+        }
+        let now = Utc::now();
+        let next_run_at = match (new_schedule.not_before, &new_schedule.recurrence) {
+            (None, None) => return Err(Self::create_schedule_invalid_error("expected a notBefore time, a recurrence or both")),
+            (Some(not_before), None) => not_before,
+            (not_before, Some(recurrence)) => {
+                let cron_expression = CronExpression::parse(recurrence).map_err(|reason| Self::create_schedule_invalid_error(&reason))?;
+                // A recurrence matching not_before to the minute runs at not_before
+                let start_time = not_before.filter(|not_before| *not_before > now).map_or(now, |not_before| not_before - TimeDelta::seconds(1));
+                cron_expression.next_after(start_time).ok_or_else(|| Self::create_schedule_invalid_error("the recurrence never comes round"))?
+            }
+        };
+        Ok(Schedule {
+            id,
+            name: new_schedule.name.trim().to_string(),
+            not_before: new_schedule.not_before,
+            recurrence: new_schedule.recurrence,
+            next_run_at: Some(next_run_at),
+            action: new_schedule.action,
+            scheduled_by: caller.clone(),
+            last_run
+        })
+    }
+
+    fn get_pump_amounts(&self, new_pump_jobs: &[NewPumpJob]) -> Result<Vec<(u8, f32)>, ApiError> {
+        if new_pump_jobs.is_empty() {
+            return Err(ApiError::new(ApiErrorCode::PumpJobsMissing));
+        }
+        new_pump_jobs.iter()
+            .map(|new_pump_job| Ok((new_pump_job.pump_number, self.units_of_measure.to_ml(&new_pump_job.amount)?)))
+            .collect()
+    }
+
+    #[cfg(feature = "bff")]
+    fn get_order_service(&self) -> Result<&OrderService, ApiError> {
+        self.order_service.as_deref().ok_or_else(|| Self::create_schedule_invalid_error("orders aren't being taken, see /diagnostics"))
+    }
+
+    pub fn start_daemon(schedule_service: &Arc<ScheduleService>) {
+        let mut daemon_thread = schedule_service.daemon_thread.lock().unwrap();
+        if daemon_thread.is_some() {
+            return;
+        }
+        let schedule_service = schedule_service.clone();
+        *daemon_thread = Some(thread::spawn(move || schedule_service.process_schedules()));
+    }
+
+    pub fn kill_daemon(&self) {
+        let (run_daemon_mutex, cvar) = &self.run_daemon_pair;
+        *run_daemon_mutex.lock().unwrap() = false;
+        cvar.notify_one();
+        if let Some(daemon_thread) = self.daemon_thread.lock().unwrap().take() {
+            daemon_thread.join().unwrap();
+        }
+    }
+
+    fn process_schedules(&self) {
+        let (run_daemon_mutex, cvar) = &self.run_daemon_pair;
+        loop {
+            self.run_due_schedules(Utc::now());
+            let run_daemon_guard = run_daemon_mutex.lock().unwrap();
+            let (run_daemon_guard, _) = cvar.wait_timeout_while(run_daemon_guard, CHECK_INTERVAL, |should_run_daemon| *should_run_daemon).unwrap();
+            if !*run_daemon_guard {
+                return;
+            }
+        }
+    }
+
+    // Anything that came due while the API was down runs once, then recurring schedules carry on from now
+    fn run_due_schedules(&self, now: DateTime<Utc>) {
+        let mut schedules = self.schedules.lock().unwrap();
+        let mut has_run = false;
+        for schedule in schedules.iter_mut().filter(|schedule| schedule.next_run_at.is_some_and(|next_run_at| next_run_at <= now)) {
+            let result = self.run_action(schedule);
+            match &result {
+                Ok(group_id) => log::info!("Schedule {} ({}) queued {}", schedule.id, schedule.name, group_id),
+                Err(error) => log::warn!("Schedule {} ({}) couldn't be queued: {:?}", schedule.id, schedule.name, error)
+            }
+            schedule.last_run = Some(ScheduleRun::new(now, &result));
+            schedule.next_run_at = schedule.recurrence.as_ref()
+                .and_then(|recurrence| CronExpression::parse(recurrence).ok())
+                .and_then(|cron_expression| cron_expression.next_after(now));
+            has_run = true;
+        }
+        if !has_run {
+            return;
+        }
+        // One-off schedules are done once they've run, unless they failed
+        schedules.retain(|schedule| schedule.next_run_at.is_some() || schedule.last_run.as_ref().is_some_and(|last_run| last_run.error_code.is_some()));
+        if let Err(error) = self.save(&schedules) {
+            log::error!("Couldn't save schedules after running them: {:?}", error);
+        }
+    }
+
+    fn run_action(&self, schedule: &Schedule) -> Result<Uuid, ApiError> {
+        match &schedule.action {
+            ScheduledAction::PumpJobs { jobs, priority } => {
+                let pump_amounts = self.get_pump_amounts(jobs)?;
+                let queued_pump_group = self.pump_service.lock().unwrap().enqueue_pump_group(Uuid::new_v4(), &pump_amounts, None, *priority, None)?;
+                Ok(queued_pump_group.group_id)
+            },
+            #[cfg(feature = "bff")]
+            ScheduledAction::Order { order } => Ok(self.get_order_service()?.place_order(&schedule.scheduled_by, order.clone())?.id)
+        }
+    }
+
+    fn save(&self, schedules: &[Schedule]) -> Result<(), ApiError> {
+        let schedules_json = serde_json::to_string_pretty(schedules).map_err(Self::create_save_error)?;
+        if let Some(schedules_directory) = self.schedules_file_path.parent() {
+            fs::create_dir_all(schedules_directory).map_err(Self::create_save_error)?;
+        }
+        fs::write(&self.schedules_file_path, schedules_json).map_err(Self::create_save_error)
+    }
+
+    fn create_schedule_not_found_error(id: Uuid) -> ApiError {
+        ApiError::with_details(ApiErrorCode::ScheduleNotFound, json!({ "id": id }))
+    }
+
+    fn create_schedule_invalid_error(reason: &str) -> ApiError {
+        ApiError::with_details(ApiErrorCode::ScheduleInvalid, json!({ "reason": reason }))
+    }
+
+    fn create_save_error<E: ToString>(error: E) -> ApiError {
+        ApiError::with_details(ApiErrorCode::SchedulesNotSaved, json!({ "error": error.to_string() }))
+    }
+}
@@ -0,0 +1,35 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::{ Arc, Mutex };
+use crate::api::models::{ Config, Schedule, UnitsOfMeasure };
+#[cfg(feature = "bff")]
+use crate::api::OrderService;
+use crate::api::{ PumpService, ScheduleService };
+
+pub struct ScheduleServiceFactory {}
+
+impl ScheduleServiceFactory {
+    pub fn create(
+        config: &Config,
+        pump_service: Arc<Mutex<PumpService>>,
+        #[cfg(feature = "bff")]
+        order_service: Option<Arc<OrderService>>
+    ) -> Result<ScheduleService, String> {
+        let file_path = config.schedules_file_path.clone();
+        let schedules: Vec<Schedule> = match fs::read_to_string(&file_path) {
+            Ok(schedules_json) => serde_json::from_str(&schedules_json)
+                .map_err(|error| format!("Couldn't parse {}: {}", file_path.display(), error))?,
+            // Nothing has been scheduled yet
+            Err(error) if error.kind() == ErrorKind::NotFound => vec![],
+            Err(error) => return Err(format!("Couldn't read {}: {}", file_path.display(), error))
+        };
+        Ok(ScheduleService::new(
+            schedules,
+            file_path,
+            pump_service,
+            UnitsOfMeasure::new(config.units_of_measure.clone()),
+            #[cfg(feature = "bff")]
+            order_service
+        ))
+    }
+}
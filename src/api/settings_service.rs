@@ -2,48 +2,40 @@ use std::path::PathBuf;
 use std::fs::OpenOptions;
 use std::fs;
 use std::io::Write;
-use std::sync::{ Arc, RwLock };
+use std::sync::RwLock;
+use serde_json::json;
+use crate::api::models::{ ApiError, ApiErrorCode };
 use crate::api::models::settings::Settings;
-use crate::api::ResourceService;
 
 pub struct SettingsService {
-    resource_service: Arc<ResourceService>,
     pub settings: RwLock<Settings>,
     settings_file_path: PathBuf
 }
 
 impl SettingsService {
-    pub fn new(resource_service: Arc<ResourceService>, settings: RwLock<Settings>, settings_file_path: PathBuf) -> SettingsService {
-        SettingsService { resource_service, settings, settings_file_path }
+    pub fn new(settings: RwLock<Settings>, settings_file_path: PathBuf) -> SettingsService {
+        SettingsService { settings, settings_file_path }
     }
 
-    pub fn save(&self, settings: Settings) -> Result<(), String> {
-        match serde_json::to_string(&settings) {
-            Ok(settings_json) => {
-                match fs::create_dir_all(self.settings_file_path.parent().unwrap()) {
-                    Ok(_) => {
-                        match OpenOptions::new().write(true).create(true).open(self.settings_file_path.clone()) {
-                            Ok(mut settings_file) => {
-                                if settings_file.set_len(0).is_err() {
-                                    return Err(self.resource_service.get_resource_string_by_name("truncating_settings_file_error_message").unwrap());
-                                }
-                                match settings_file.write_all(settings_json.as_bytes()) {
-                                    Ok(_) => {
-                                        *self.settings.write().unwrap() = settings;
-                                        Ok(())
-                                    },
-                                    Err(error) => Err(self.resource_service.get_resource_string_by_name("write_to_settings_file_error_message_template").unwrap() + &error.to_string())
-                                }
-                            }
-                            Err(error) => Err(self.resource_service.get_resource_string_by_name("create_or_open_settings_file_error_message_template").unwrap() + &error.to_string())
-                        }
-                    },
-                    Err(error) => {
-                        return Err(self.resource_service.get_resource_string_by_name("create_settings_directory_error_message_template").unwrap() + &error.to_string())
-                    }
-                }
-            }
-            Err(error) => Err(self.resource_service.get_resource_string_by_name("settings_serialization_error_message_template").unwrap() + &error.to_string())
+    pub fn save(&self, settings: Settings) -> Result<(), ApiError> {
+        let settings_json = serde_json::to_string(&settings)
+            .map_err(|error| Self::create_save_error(ApiErrorCode::SettingsSerializationFailed, error))?;
+        if let Some(settings_directory) = self.settings_file_path.parent() {
+            fs::create_dir_all(settings_directory)
+                .map_err(|error| Self::create_save_error(ApiErrorCode::SettingsDirectoryNotCreated, error))?;
         }
+        // Truncated separately so that failure is reported on its own
+        let mut settings_file = OpenOptions::new().write(true).create(true).truncate(false).open(&self.settings_file_path)
+            .map_err(|error| Self::create_save_error(ApiErrorCode::SettingsFileNotOpened, error))?;
+        settings_file.set_len(0)
+            .map_err(|error| Self::create_save_error(ApiErrorCode::SettingsFileNotTruncated, error))?;
+        settings_file.write_all(settings_json.as_bytes())
+            .map_err(|error| Self::create_save_error(ApiErrorCode::SettingsFileNotWritten, error))?;
+        *self.settings.write().unwrap() = settings;
+        Ok(())
+    }
+
+    fn create_save_error<E: ToString>(code: ApiErrorCode, error: E) -> ApiError {
+        ApiError::with_details(code, json!({ "error": error.to_string() }))
     }
 }
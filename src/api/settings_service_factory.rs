@@ -1,29 +1,26 @@
 use std::fs;
-use std::sync::{ RwLock, Arc };
+use std::io::ErrorKind;
+use std::sync::RwLock;
+use crate::api::models::Config;
 use crate::api::models::settings::Settings;
-use crate::api::{ ResourceService, SettingsService };
+use crate::api::SettingsService;
 
 pub struct SettingsServiceFactory {}
 
 impl SettingsServiceFactory {
-    pub fn create_or_panic(resource_service: Arc<ResourceService>, number_of_pumps: u8) -> SettingsService {
-        let home_dir = dirs::home_dir().unwrap();
-        let settings_file_path = dotenv::var("SETTINGS_FILE_PATH").unwrap();
-        let file_path = home_dir.join(settings_file_path);
-        let settings: Settings;
-        match fs::read_to_string(file_path.clone()) {
-            Ok(existing_settings_json) => {
-                settings = serde_json::from_str(&existing_settings_json).unwrap();
-            }
-            Err(_) => {
-                settings = Settings::new(number_of_pumps);
-            }
-        }
+    pub fn create(config: &Config) -> Result<SettingsService, String> {
+        let file_path = config.settings_file_path.clone();
+        let settings: Settings = match fs::read_to_string(&file_path) {
+            Ok(existing_settings_json) => serde_json::from_str(&existing_settings_json)
+                .map_err(|error| format!("Couldn't parse {}: {}", file_path.display(), error))?,
+            // No settings have been saved yet
+            Err(error) if error.kind() == ErrorKind::NotFound => Settings::new(config.get_number_of_pumps()),
+            Err(error) => return Err(format!("Couldn't read {}: {}", file_path.display(), error))
+        };
         
-        SettingsService::new(
-            resource_service,
+        Ok(SettingsService::new(
             RwLock::new(settings),
             file_path
-        )
+        ))
     }
 }
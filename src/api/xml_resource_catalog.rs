@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use handlebars::Handlebars;
+use serde_json::Value;
+use crate::api::models::resources_xml::ResourcesElement;
+use crate::api::ResourceCatalog;
+
+// Strings from a strings.xml file with every handlebars template compiled up front
+pub struct XmlResourceCatalog {
+    strings: HashMap<String, String>,
+    handlebars: Handlebars<'static>
+}
+
+impl XmlResourceCatalog {
+    // Returns the catalog along with every string that didn't compile
+    pub fn new(resource_element: ResourcesElement) -> (XmlResourceCatalog, Vec<String>) {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_escape_fn(handlebars::no_escape);
+        let mut strings = HashMap::new();
+        let mut errors = vec![];
+        for string_element in resource_element.strings {
+            match handlebars.register_template_string(&string_element.name, &string_element.content) {
+                Ok(_) => {
+                    strings.insert(string_element.name, string_element.content);
+                },
+                Err(error) => {
+                    let error_lines: Vec<String> = error.to_string().lines().map(|line| line.trim().to_string()).collect();
+                    errors.push(format!("String \"{}\" doesn't compile: {}", string_element.name, error_lines.join(" ")));
+                }
+            }
+        }
+        (XmlResourceCatalog { strings, handlebars }, errors)
+    }
+}
+
+impl ResourceCatalog for XmlResourceCatalog {
+    fn contains(&self, name: &str) -> bool {
+        self.strings.contains_key(name)
+    }
+
+    fn get_string(&self, name: &str) -> Option<String> {
+        self.strings.get(name).cloned()
+    }
+
+    fn render(&self, name: &str, data: &Value) -> Option<String> {
+        self.handlebars.render(name, data).ok()
+    }
+}
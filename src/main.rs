@@ -1,165 +1,688 @@
 mod api;
 
+use std::fs;
+use std::path::PathBuf;
 use std::sync::{ Mutex, Arc };
+use std::time::Duration;
+use hard_xml::XmlRead;
 #[macro_use] extern crate rocket;
 extern crate env_logger;
-use rocket::http::Header;
-use rocket::{ Rocket, Response, Request, State, Build, Route };
-use rocket::fairing::{ Info, Fairing, Kind };
+use rocket::http::Status;
+use rocket::{ Rocket, Request, State, Build, Route };
 use rocket::response::status;
 use rocket::serde::json::Json;
-use crate::api::models::{ PumpState, PumpJob, GenericError };
+use serde_json::json;
+use crate::api::models::resources_xml::ResourcesElement;
+use uuid::Uuid;
+use crate::api::models::{ PumpState, AcceptedLocales, ApiError, ApiErrorCode, Caller, CleaningProgram, CleaningRun, Config, CreatedApiKey, DiagnosticsReport, DiagnosticStatus, JobPriority, ListedApiKey, MaintenanceReminder, MaintenanceTask, NewApiKey, NewCleaningRun, NewPumpJob, NewSchedule, PourAmount, PumpJobKind, PumpJobMove, PumpQueueSummary, QueuedPumpGroup, QueuedPumpJob, Schedule, UnitsOfMeasure };
 #[cfg(feature = "bff")]
 use crate::api::models::settings::Settings;
 #[cfg(feature = "bff")]
-use crate::api::{ SettingsService, SettingsServiceFactory };
+use crate::api::models::{ GuestProfile, GuestProfileReport, MenuDrink, NewGuestProfile, NewOrder, Order };
+#[cfg(feature = "bff")]
+use crate::api::{ DrinkService, GuestService, GuestServiceFactory, OrderService, SettingsService, SettingsServiceFactory };
 use crate::api::{
+    AccessFailure,
+    AdminAccess,
+    ApiKeyService,
+    ApiKeyServiceFactory,
+    BartenderAccess,
+    ConfigFactory,
+    CorsFairing,
+    FluentConverter,
+    GuestAccess,
+    HealthService,
+    Idempotency,
+    IdempotencyService,
+    InstanceLock,
+    LocalAdminAccess,
     PumpService,
     PumpServiceFactory,
-    ResourceService,
-    ResourceServiceFactory
+    RequestedCup,
+    ResourceServiceFactory,
+    ScheduleService,
+    ScheduleServiceFactory,
+    CleaningService,
+    CleaningServiceFactory,
+    RouteMethods
 };
 
-#[options("/pumps")]
-fn pumps_options() -> () { }
+const CONVERT_STRINGS_COMMAND: &str = "convert-strings";
 
 #[get("/pumps")]
-fn pumps_get(pump_service: &State<Arc<Mutex<PumpService>>>) -> Json<Vec<PumpState>> {
+fn pumps_get(_access: GuestAccess, pump_service: &State<Arc<Mutex<PumpService>>>) -> Json<Vec<PumpState>> {
     Json(pump_service.lock().unwrap().get_pump_states())
 }
 
-#[options("/pump_queue")]
-fn pump_queue_options() -> () { }
-
 #[get("/pump_queue")]
-fn pump_queue_get(pump_service: &State<Arc<Mutex<PumpService>>>) -> Json<Vec<PumpJob>> {
+fn pump_queue_get(_access: GuestAccess, pump_service: &State<Arc<Mutex<PumpService>>>) -> Json<Vec<QueuedPumpJob>> {
     Json(pump_service.lock().unwrap().get_pump_queue())
 }
 
-#[options("/pumps/<_pump_number>")]
-fn pump_number_options(_pump_number: u8) -> () { }
+#[get("/pump_queue/summary")]
+fn pump_queue_summary_get(_access: GuestAccess, pump_service: &State<Arc<Mutex<PumpService>>>) -> Json<PumpQueueSummary> {
+    Json(pump_service.lock().unwrap().get_pump_queue_summary())
+}
 
-#[get("/pumps/<pump_number>")]
-fn pump_number_get(pump_service: &State<Arc<Mutex<PumpService>>>, pump_number: u8) -> Result<Json<PumpState>, status::BadRequest::<Json<GenericError>>> {
-    match pump_service.lock().unwrap().get_pump_state(pump_number) {
-        Ok(pump_state) => Ok(Json(pump_state)),
-        Err(error) => Err(status::BadRequest(Some(Json(GenericError { message: error.to_string() }))))
+#[delete("/pump_queue")]
+fn pump_queue_delete(_access: BartenderAccess, pump_service: &State<Arc<Mutex<PumpService>>>) -> Json<Vec<QueuedPumpJob>> {
+    Json(pump_service.lock().unwrap().clear_pending_jobs())
+}
+
+#[post("/pump_queue/<id>/move", data = "<pump_job_move_json>")]
+fn pump_queue_move_post(_access: BartenderAccess, pump_service: &State<Arc<Mutex<PumpService>>>, id: &str, pump_job_move_json: Json<PumpJobMove>) -> Result<Json<Vec<QueuedPumpJob>>, ApiError> {
+    let id = Uuid::parse_str(id).map_err(|_| ApiError::with_details(ApiErrorCode::PumpJobNotFound, json!({ "id": id })))?;
+    pump_service.lock().unwrap().move_pending(id, pump_job_move_json.into_inner()).map(Json)
+}
+
+#[post("/pump_queue/stop")]
+fn pump_queue_stop_post(_access: BartenderAccess, pump_service: &State<Arc<Mutex<PumpService>>>, cleaning_service: Option<&State<Arc<CleaningService>>>) -> Json<Vec<QueuedPumpJob>> {
+    // Otherwise the program would just carry on with its next step
+    if let Some(cleaning_service) = cleaning_service {
+        let _ = cleaning_service.cancel_cleaning_run();
+    }
+    Json(pump_service.lock().unwrap().stop())
+}
+
+#[post("/pump_queue?<priority>", data = "<new_pump_jobs>")]
+fn pump_queue_post(_access: AdminAccess, idempotency: Idempotency<'_>, requested_cup: RequestedCup, pump_service: &State<Arc<Mutex<PumpService>>>, units_of_measure: &State<UnitsOfMeasure>, priority: Option<&str>, new_pump_jobs: Json<Vec<NewPumpJob>>) -> Result<status::Accepted::<Json<QueuedPumpGroup>>, ApiError> {
+    let mut pump_amounts = vec![];
+    for new_pump_job in new_pump_jobs.iter() {
+        pump_amounts.push((new_pump_job.pump_number, units_of_measure.to_ml(&new_pump_job.amount)?));
     }
+    let cup_capacity = requested_cup.0?;
+    let queued_pump_group = idempotency.run_once(|| pump_service.lock().unwrap().enqueue_pump_group(Uuid::new_v4(), &pump_amounts, cup_capacity, parse_priority(priority)?, None))?;
+    Ok(status::Accepted(Some(Json(queued_pump_group))))
+}
+
+fn parse_priority(priority: Option<&str>) -> Result<JobPriority, ApiError> {
+    match priority {
+        Some(priority) => serde_json::from_value(json!(priority.to_lowercase()))
+            .map_err(|_| ApiError::with_details(ApiErrorCode::PriorityInvalid, json!({ "priority": priority }))),
+        None => Ok(JobPriority::default())
+    }
+}
+
+#[get("/pumps/<pump_number>")]
+fn pump_number_get(_access: GuestAccess, pump_service: &State<Arc<Mutex<PumpService>>>, pump_number: u8) -> Result<Json<PumpState>, ApiError> {
+    pump_service.lock().unwrap().get_pump_state(pump_number).map(Json)
 }
 
-#[post("/pumps/<pump_number>", data = "<ml_to_pump_input>")]
-fn pump_number_post(resource_service: &State<Arc<ResourceService>>, pump_service: &State<Arc<Mutex<PumpService>>>, pump_number: u8, ml_to_pump_input: String) -> Result<status::Accepted::<Json<Vec<PumpJob>>>, status::BadRequest::<Json<GenericError>>> {
+#[post("/pumps/<pump_number>?<priority>&<group>", data = "<ml_to_pump_input>")]
+#[allow(clippy::too_many_arguments)]
+fn pump_number_post(_access: AdminAccess, idempotency: Idempotency<'_>, requested_cup: RequestedCup, pump_service: &State<Arc<Mutex<PumpService>>>, units_of_measure: &State<UnitsOfMeasure>, pump_number: u8, priority: Option<&str>, group: Option<&str>, ml_to_pump_input: String) -> Result<status::Accepted::<Json<Vec<QueuedPumpJob>>>, ApiError> {
     let temp = ml_to_pump_input.trim();
     if temp.is_empty() {
-        let expected_ml_to_pump_message = resource_service.get_resource_string_by_name("expected_ml_to_pump_error_message").unwrap();
-        return Err(status::BadRequest(Some(Json(GenericError { message: expected_ml_to_pump_message }))));
-    }
-    match temp.parse::<u32>() {
-        Ok(ml_to_pump) => match pump_service.lock().unwrap().enqueue_pump(pump_number, ml_to_pump) {
-            Ok(pump_queue) => Ok(status::Accepted(Some(Json(pump_queue)))),
-            Err(error) => Err(status::BadRequest(Some(Json(GenericError { message: error.to_string() }))))
-        },
-        Err(_) => {
-            let ml_to_pump_parse_message = resource_service.get_resource_string_by_name("ml_to_pump_parse_error_message").unwrap();
-            Err(status::BadRequest(Some(Json(GenericError { message: ml_to_pump_parse_message }))))
-        }
+        return Err(ApiError::new(ApiErrorCode::MlToPumpMissing));
     }
+    // A JSON body gives the amount in any unit, a plain one is still read as ml
+    let ml_to_pump = if temp.starts_with('{') {
+        let pour_amount = serde_json::from_str::<PourAmount>(temp).map_err(|_| ApiError::new(ApiErrorCode::PourAmountInvalid))?;
+        units_of_measure.to_ml(&pour_amount)?
+    } else {
+        temp.parse::<f32>().map_err(|_| ApiError::with_details(ApiErrorCode::MlToPumpNotANumber, json!({ "ml_to_pump": temp })))?
+    };
+    let cup_capacity = requested_cup.0?;
+    let group_id = group.map(|group| Uuid::parse_str(group).map_err(|_| ApiError::with_details(ApiErrorCode::PumpJobNotFound, json!({ "id": group })))).transpose()?;
+    let pump_queue = idempotency.run_once(|| pump_service.lock().unwrap().enqueue_pump(pump_number, ml_to_pump, cup_capacity, group_id, parse_priority(priority)?))?;
+    Ok(status::Accepted(Some(Json(pump_queue))))
 }
 
-#[cfg(feature = "bff")]
-#[options("/settings")]
-fn settings_options() -> () { }
+#[post("/pumps/<pump_number>/prime?<priority>")]
+fn pump_number_prime_post(_access: BartenderAccess, idempotency: Idempotency<'_>, pump_service: &State<Arc<Mutex<PumpService>>>, pump_number: u8, priority: Option<&str>) -> Result<status::Accepted<Json<QueuedPumpGroup>>, ApiError> {
+    enqueue_line_jobs(idempotency, pump_service, &[pump_number], PumpJobKind::Prime, priority)
+}
+
+#[post("/pumps/<pump_number>/purge?<priority>")]
+fn pump_number_purge_post(_access: BartenderAccess, idempotency: Idempotency<'_>, pump_service: &State<Arc<Mutex<PumpService>>>, pump_number: u8, priority: Option<&str>) -> Result<status::Accepted<Json<QueuedPumpGroup>>, ApiError> {
+    enqueue_line_jobs(idempotency, pump_service, &[pump_number], PumpJobKind::Purge, priority)
+}
+
+#[post("/pumps/prime?<priority>")]
+fn pumps_prime_post(_access: BartenderAccess, idempotency: Idempotency<'_>, pump_service: &State<Arc<Mutex<PumpService>>>, priority: Option<&str>) -> Result<status::Accepted<Json<QueuedPumpGroup>>, ApiError> {
+    let pump_numbers: Vec<u8> = (1..=pump_service.lock().unwrap().get_number_of_pumps()).collect();
+    enqueue_line_jobs(idempotency, pump_service, &pump_numbers, PumpJobKind::Prime, priority)
+}
+
+#[post("/pumps/purge?<priority>")]
+fn pumps_purge_post(_access: BartenderAccess, idempotency: Idempotency<'_>, pump_service: &State<Arc<Mutex<PumpService>>>, priority: Option<&str>) -> Result<status::Accepted<Json<QueuedPumpGroup>>, ApiError> {
+    let pump_numbers: Vec<u8> = (1..=pump_service.lock().unwrap().get_number_of_pumps()).collect();
+    enqueue_line_jobs(idempotency, pump_service, &pump_numbers, PumpJobKind::Purge, priority)
+}
+
+fn enqueue_line_jobs(idempotency: Idempotency<'_>, pump_service: &State<Arc<Mutex<PumpService>>>, pump_numbers: &[u8], kind: PumpJobKind, priority: Option<&str>) -> Result<status::Accepted<Json<QueuedPumpGroup>>, ApiError> {
+    let priority = parse_priority(priority)?;
+    let queued_pump_group = idempotency.run_once(|| pump_service.lock().unwrap().enqueue_line_jobs(pump_numbers, kind, priority))?;
+    Ok(status::Accepted(Some(Json(queued_pump_group))))
+}
+
+#[get("/maintenance")]
+fn maintenance_get(_access: BartenderAccess, accepted_locales: AcceptedLocales, pump_service: &State<Arc<Mutex<PumpService>>>) -> Json<Vec<MaintenanceReminder>> {
+    Json(pump_service.lock().unwrap().get_maintenance_reminders(&accepted_locales.locales))
+}
+
+#[post("/maintenance/<pump_number>/<task>")]
+fn maintenance_task_post(_access: BartenderAccess, pump_service: &State<Arc<Mutex<PumpService>>>, pump_number: u8, task: &str) -> Result<Json<PumpState>, ApiError> {
+    let maintenance_task: MaintenanceTask = serde_json::from_value(json!(task.to_lowercase()))
+        .map_err(|_| ApiError::with_details(ApiErrorCode::MaintenanceTaskNotFound, json!({ "task": task })))?;
+    pump_service.lock().unwrap().record_maintenance(pump_number, maintenance_task).map(Json)
+}
+
+#[get("/schedules")]
+fn schedules_get(_access: BartenderAccess, schedule_service: &State<Arc<ScheduleService>>) -> Json<Vec<Schedule>> {
+    Json(schedule_service.get_schedules())
+}
+
+#[post("/schedules", format = "application/json", data = "<new_schedule_json>")]
+fn schedules_post(access: AdminAccess, schedule_service: &State<Arc<ScheduleService>>, new_schedule_json: Json<NewSchedule>) -> Result<status::Created<Json<Schedule>>, ApiError> {
+    let schedule = schedule_service.create_schedule(&access.0, new_schedule_json.into_inner())?;
+    Ok(status::Created::new(format!("/schedules/{}", schedule.id)).body(Json(schedule)))
+}
+
+#[put("/schedules/<id>", format = "application/json", data = "<new_schedule_json>")]
+fn schedule_put(access: AdminAccess, schedule_service: &State<Arc<ScheduleService>>, id: &str, new_schedule_json: Json<NewSchedule>) -> Result<Json<Schedule>, ApiError> {
+    schedule_service.update_schedule(&access.0, parse_schedule_id(id)?, new_schedule_json.into_inner()).map(Json)
+}
+
+#[delete("/schedules/<id>")]
+fn schedule_delete(_access: AdminAccess, schedule_service: &State<Arc<ScheduleService>>, id: &str) -> Result<status::NoContent, ApiError> {
+    schedule_service.delete_schedule(parse_schedule_id(id)?)?;
+    Ok(status::NoContent)
+}
+
+fn parse_schedule_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|_| ApiError::with_details(ApiErrorCode::ScheduleNotFound, json!({ "id": id })))
+}
+
+#[get("/cleaning/programs")]
+fn cleaning_programs_get(_access: BartenderAccess, cleaning_service: &State<Arc<CleaningService>>) -> Json<Vec<CleaningProgram>> {
+    Json(cleaning_service.get_cleaning_programs())
+}
+
+#[get("/cleaning/run")]
+fn cleaning_run_get(_access: GuestAccess, cleaning_service: &State<Arc<CleaningService>>) -> Result<Json<CleaningRun>, ApiError> {
+    cleaning_service.get_cleaning_run().map(Json)
+}
+
+#[post("/cleaning/run", format = "application/json", data = "<new_cleaning_run_json>")]
+fn cleaning_run_post(_access: BartenderAccess, cleaning_service: &State<Arc<CleaningService>>, new_cleaning_run_json: Json<NewCleaningRun>) -> Result<status::Accepted<Json<CleaningRun>>, ApiError> {
+    let cleaning_run = CleaningService::start_cleaning_run(cleaning_service, new_cleaning_run_json.into_inner())?;
+    Ok(status::Accepted(Some(Json(cleaning_run))))
+}
+
+#[post("/cleaning/run/confirm")]
+fn cleaning_run_confirm_post(_access: BartenderAccess, cleaning_service: &State<Arc<CleaningService>>) -> Result<Json<CleaningRun>, ApiError> {
+    cleaning_service.confirm_prompt().map(Json)
+}
+
+#[delete("/cleaning/run")]
+fn cleaning_run_delete(_access: BartenderAccess, cleaning_service: &State<Arc<CleaningService>>) -> Result<Json<CleaningRun>, ApiError> {
+    cleaning_service.cancel_cleaning_run().map(Json)
+}
+
+#[get("/diagnostics")]
+fn diagnostics_get(_access: AdminAccess, diagnostics_report: &State<DiagnosticsReport>) -> Json<DiagnosticsReport> {
+    Json(diagnostics_report.inner().clone())
+}
+
+#[get("/health/live")]
+fn health_live_get() -> Json<DiagnosticsReport> {
+    // Being able to answer at all means the process is alive
+    let mut liveness_report = DiagnosticsReport::default();
+    liveness_report.add("process", DiagnosticStatus::Pass, "API is responding".to_string());
+    Json(liveness_report)
+}
+
+#[get("/health/ready")]
+fn health_ready_get(health_service: &State<HealthService>) -> status::Custom<Json<DiagnosticsReport>> {
+    let readiness_report = health_service.get_readiness_report();
+    if readiness_report.has_failures() {
+        return status::Custom(Status::ServiceUnavailable, Json(readiness_report));
+    }
+    status::Custom(Status::Ok, Json(readiness_report))
+}
 
 #[cfg(feature = "bff")]
 #[get("/settings")]
-fn settings_get(settings_service: &State<Arc<SettingsService>>) -> Json<Settings> {
+fn settings_get(_access: GuestAccess, settings_service: &State<Arc<SettingsService>>) -> Json<Settings> {
     Json(settings_service.settings.read().unwrap().clone())
 }
 
 #[cfg(feature = "bff")]
 #[put("/settings", format = "application/json", data = "<settings_json>")]
-fn settings_put(resource_service: &State<Arc<ResourceService>>, settings_service: &State<Arc<SettingsService>>, settings_json: Json<Settings>) -> Result<status::NoContent, status::BadRequest::<Json<GenericError>>> {
+fn settings_put(_access: AdminAccess, settings_service: &State<Arc<SettingsService>>, pump_service: Option<&State<Arc<Mutex<PumpService>>>>, settings_json: Json<Settings>) -> Result<status::NoContent, ApiError> {
     let settings = settings_json.into_inner();
     if !settings.is_valid() {
-        let settings_invalid_message = resource_service.get_resource_string_by_name("invalid_settings_error_message").unwrap();
-        return Err(status::BadRequest(Some(Json(GenericError { message: settings_invalid_message }))));
+        return Err(ApiError::new(ApiErrorCode::SettingsInvalid));
     }
-    match settings_service.save(settings) {
-        Ok(_) => Ok(status::NoContent),
-        Err(save_error) => Err(status::BadRequest(Some(Json(GenericError { message: save_error.to_string() }))))
+    // A pump given another ingredient has a new bottle on its line
+    let changed_pump_numbers: Vec<u8> = {
+        let previous_settings = settings_service.settings.read().unwrap();
+        settings.pumps.iter()
+            .filter(|pump| !previous_settings.pumps.iter().any(|previous_pump| previous_pump.pump_number == pump.pump_number && previous_pump.ingredient_id == pump.ingredient_id))
+            .map(|pump| pump.pump_number)
+            .collect()
+    };
+    settings_service.save(settings)?;
+    if let Some(pump_service) = pump_service {
+        pump_service.lock().unwrap().mark_unprimed(&changed_pump_numbers);
     }
+    Ok(status::NoContent)
+}
+
+// Strength is worked out for the cup given with ?cup=<id>, otherwise each drink's default cup
+#[cfg(feature = "bff")]
+#[get("/drinks?<cup>")]
+fn drinks_get(_access: GuestAccess, drink_service: &State<Arc<DrinkService>>, cup: Option<&str>) -> Result<Json<Vec<MenuDrink>>, ApiError> {
+    let cup_id = match cup {
+        Some(cup) => Some(Uuid::parse_str(cup).map_err(|_| ApiError::with_details(ApiErrorCode::CupNotFound, json!({ "cup_id": cup })))?),
+        None => None
+    };
+    drink_service.get_menu(cup_id).map(Json)
 }
 
-pub struct CORS;
+#[cfg(feature = "bff")]
+#[post("/orders", format = "application/json", data = "<new_order_json>")]
+fn orders_post(access: GuestAccess, idempotency: Idempotency<'_>, order_service: &State<Arc<OrderService>>, new_order_json: Json<NewOrder>) -> Result<status::Accepted<Json<Order>>, ApiError> {
+    let order = idempotency.run_once(|| order_service.place_order(&access.0, new_order_json.into_inner()))?;
+    Ok(status::Accepted(Some(Json(order))))
+}
 
-#[rocket::async_trait]
-impl Fairing for CORS {
-    fn info(&self) -> Info {
-        Info {
-            name: "Attaching CORS headers to responses",
-            kind: Kind::Response
-        }
-    }
+#[cfg(feature = "bff")]
+#[get("/guests")]
+fn guests_get(_access: BartenderAccess, guest_service: &State<Arc<GuestService>>) -> Json<Vec<GuestProfile>> {
+    Json(guest_service.get_guests())
+}
+
+#[cfg(feature = "bff")]
+#[post("/guests", format = "application/json", data = "<new_guest_profile_json>")]
+fn guests_post(_access: BartenderAccess, guest_service: &State<Arc<GuestService>>, new_guest_profile_json: Json<NewGuestProfile>) -> Result<status::Created<Json<GuestProfile>>, ApiError> {
+    let guest_profile = guest_service.create_guest(new_guest_profile_json.into_inner())?;
+    Ok(status::Created::new(format!("/guests/{}", guest_profile.id)).body(Json(guest_profile)))
+}
+
+#[cfg(feature = "bff")]
+#[get("/guests/<id>")]
+fn guest_get(access: GuestAccess, guest_service: &State<Arc<GuestService>>, id: &str) -> Result<Json<GuestProfileReport>, ApiError> {
+    guest_service.get_guest_report(&access.0, parse_guest_id(id)?).map(Json)
+}
+
+#[cfg(feature = "bff")]
+#[put("/guests/<id>", format = "application/json", data = "<new_guest_profile_json>")]
+fn guest_put(_access: BartenderAccess, guest_service: &State<Arc<GuestService>>, id: &str, new_guest_profile_json: Json<NewGuestProfile>) -> Result<Json<GuestProfile>, ApiError> {
+    guest_service.update_guest(parse_guest_id(id)?, new_guest_profile_json.into_inner()).map(Json)
+}
+
+#[cfg(feature = "bff")]
+#[delete("/guests/<id>")]
+fn guest_delete(_access: BartenderAccess, guest_service: &State<Arc<GuestService>>, id: &str) -> Result<status::NoContent, ApiError> {
+    guest_service.delete_guest(parse_guest_id(id)?)?;
+    Ok(status::NoContent)
+}
+
+#[cfg(feature = "bff")]
+fn parse_guest_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|_| ApiError::with_details(ApiErrorCode::GuestNotFound, json!({ "guest_id": id })))
+}
+
+// Lets a client find out which role its key has
+#[get("/me")]
+fn me_get(access: GuestAccess) -> Json<Caller> {
+    Json(access.0)
+}
 
-    async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
-        response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
-        response.set_header(Header::new("Access-Control-Allow-Methods", "POST, GET, PUT, OPTIONS"));
-        response.set_header(Header::new("Access-Control-Allow-Headers", "Content-Type"));
+#[get("/api_keys")]
+fn api_keys_get(_access: LocalAdminAccess, api_key_service: &State<ApiKeyService>) -> Json<Vec<ListedApiKey>> {
+    Json(api_key_service.get_api_keys())
+}
+
+#[post("/api_keys", format = "application/json", data = "<new_api_key_json>")]
+fn api_keys_post(_access: LocalAdminAccess, api_key_service: &State<ApiKeyService>, new_api_key_json: Json<NewApiKey>) -> Result<status::Created<Json<CreatedApiKey>>, ApiError> {
+    let created_api_key = api_key_service.create_api_key(new_api_key_json.into_inner())?;
+    Ok(status::Created::new(format!("/api_keys/{}", created_api_key.id)).body(Json(created_api_key)))
+}
+
+#[delete("/api_keys/<id>")]
+fn api_key_delete(_access: LocalAdminAccess, api_key_service: &State<ApiKeyService>, id: &str) -> Result<status::NoContent, ApiError> {
+    let id = Uuid::parse_str(id).map_err(|_| ApiError::with_details(ApiErrorCode::ApiKeyNotFound, json!({ "id": id })))?;
+    api_key_service.delete_api_key(id)?;
+    Ok(status::NoContent)
+}
+
+// Answers preflight requests for every mounted route; the CORS fairing adds which methods are allowed
+#[options("/<_path..>")]
+fn preflight_options(_path: PathBuf, route_methods: RouteMethods) -> Result<status::NoContent, ApiError> {
+    if route_methods.0.is_empty() {
+        return Err(ApiError::new(ApiErrorCode::RouteNotFound));
     }
+    Ok(status::NoContent)
+}
+
+// Rocket answers JSON syntax errors with a 400, which is kept rather than folded into the 422
+#[catch(400)]
+fn bad_request_catcher() -> status::Custom<ApiError> {
+    status::Custom(Status::BadRequest, ApiError::new(ApiErrorCode::MalformedRequest))
+}
+
+#[catch(401)]
+fn unauthorized_catcher(request: &Request) -> ApiError {
+    AccessFailure::take_error(request, ApiErrorCode::AuthenticationRequired)
+}
+
+#[catch(403)]
+fn forbidden_catcher(request: &Request) -> ApiError {
+    AccessFailure::take_error(request, ApiErrorCode::InsufficientRole)
+}
+
+#[catch(404)]
+fn not_found_catcher() -> ApiError {
+    ApiError::new(ApiErrorCode::RouteNotFound)
+}
+
+#[catch(422)]
+fn unprocessable_entity_catcher() -> ApiError {
+    ApiError::new(ApiErrorCode::MalformedRequest)
+}
+
+// Also reached when a handler panics
+#[catch(500)]
+fn internal_server_error_catcher() -> ApiError {
+    ApiError::new(ApiErrorCode::InternalError)
 }
 
 #[cfg(feature = "bff")]
-fn optionally_attach_settings_endpoint(rocket_builder: Rocket<Build>, routes: &mut Vec<Route>, resource_service_arc: Arc<ResourceService>, number_of_pumps: u8) -> Rocket<Build> {
-    // Add routes
-    routes.append(&mut routes![settings_options, settings_get, settings_put]);
+fn optionally_attach_bff_endpoints(
+    mut rocket_builder: Rocket<Build>,
+    routes: &mut Vec<Route>,
+    diagnostics_report: &mut DiagnosticsReport,
+    config: Option<&Config>,
+    pump_service: Option<Arc<Mutex<PumpService>>>
+) -> Rocket<Build> {
     // Create settings service
-    let settings_service = SettingsServiceFactory::create_or_panic(resource_service_arc, number_of_pumps);
-    let settings_service_arc = Arc::new(settings_service);
-    rocket_builder.manage(settings_service_arc)
+    let settings_service = run_check(diagnostics_report, "settings_file", config, |config| {
+        let pass_message = if config.settings_file_path.exists() {
+            format!("Loaded {}", config.settings_file_path.display())
+        }
+        else {
+            format!("{} doesn't exist yet; starting with empty settings", config.settings_file_path.display())
+        };
+        (SettingsServiceFactory::create(config), pass_message)
+    }).map(Arc::new);
+    let drink_service = settings_service.as_ref().zip(config).map(|(settings_service, config)| {
+        Arc::new(DrinkService::new(settings_service.clone(), config.standard_drink_grams, config.shot_ml, config.default_cup_fill_ratio, config.cup_overflow_policy))
+    });
+    if let Some((settings_service, drink_service)) = settings_service.as_ref().zip(drink_service.as_ref()) {
+        routes.append(&mut routes![settings_get, settings_put, drinks_get]);
+        rocket_builder = rocket_builder.manage(settings_service.clone()).manage(drink_service.clone());
+    }
+    // Create guest service
+    let guest_service = run_check(diagnostics_report, "guests_file", config, |config| {
+        let pass_message = format!("Loaded guests from {} with {} consumption limits", config.guests_file_path.display(), config.consumption_limits.len());
+        (GuestServiceFactory::create(config, pump_service.clone()), pass_message)
+    }).map(Arc::new);
+    if let Some(guest_service) = &guest_service {
+        routes.append(&mut routes![guests_get, guests_post, guest_get, guest_put, guest_delete]);
+        rocket_builder = rocket_builder.manage(guest_service.clone());
+    }
+    // Orders need everything else so limits can't be skipped
+    if let (Some(settings_service), Some(pump_service), Some(guest_service), Some(drink_service)) = (settings_service, pump_service, guest_service, drink_service) {
+        routes.append(&mut routes![orders_post]);
+        rocket_builder = rocket_builder.manage(Arc::new(OrderService::new(settings_service, pump_service, guest_service, drink_service)));
+    }
+    rocket_builder
 }
 
 #[cfg(not(feature = "bff"))]
-fn optionally_attach_settings_endpoint(rocket_builder: Rocket<Build>, _routes: &mut Vec<Route>, _resource_service: Arc<ResourceService>, _number_of_pumps: u8) -> Rocket<Build> { rocket_builder }
+fn optionally_attach_bff_endpoints(
+    rocket_builder: Rocket<Build>,
+    _routes: &mut Vec<Route>,
+    _diagnostics_report: &mut DiagnosticsReport,
+    _config: Option<&Config>,
+    _pump_service: Option<Arc<Mutex<PumpService>>>
+) -> Rocket<Build> { rocket_builder }
+
+// Runs a startup check once its dependency is available and records the outcome
+fn run_check<D, T>(diagnostics_report: &mut DiagnosticsReport, name: &str, dependency: Option<D>, check: impl FnOnce(D) -> (Result<T, String>, String)) -> Option<T> {
+    match dependency {
+        Some(dependency) => {
+            let (result, pass_message) = check(dependency);
+            diagnostics_report.add_result(name, &result, pass_message);
+            result.ok()
+        },
+        None => {
+            diagnostics_report.add_skipped(name, "a check it depends on failed");
+            None
+        }
+    }
+}
+
+// Handles "drink-o-matic convert-strings <strings.xml> <strings.ftl>"
+fn convert_strings_to_fluent(arguments: &[String]) -> i32 {
+    let (input_file_path, output_file_path) = match arguments {
+        [input_file_path, output_file_path] => (input_file_path, output_file_path),
+        _ => {
+            eprintln!("Usage: drink-o-matic {} <strings.xml> <strings.ftl>", CONVERT_STRINGS_COMMAND);
+            return 2;
+        }
+    };
+    let resource_element = match fs::read_to_string(input_file_path).map_err(|error| error.to_string())
+        .and_then(|content| ResourcesElement::from_str(&content).map_err(|error| error.to_string())) {
+        Ok(resource_element) => resource_element,
+        Err(error) => {
+            eprintln!("Couldn't read {}: {}", input_file_path, error);
+            return 1;
+        }
+    };
+    let (ftl, conversion_errors) = FluentConverter::convert(&resource_element);
+    for conversion_error in &conversion_errors {
+        eprintln!("Skipped: {}", conversion_error);
+    }
+    if let Err(error) = fs::write(output_file_path, ftl) {
+        eprintln!("Couldn't write {}: {}", output_file_path, error);
+        return 1;
+    }
+    println!("Converted {} of {} strings to {}", resource_element.strings.len() - conversion_errors.len(), resource_element.strings.len(), output_file_path);
+    0
+}
 
 #[rocket::main]
-async fn main() -> Result<(), rocket::Error> {
+async fn main() -> Result<(), Box<rocket::Error>> {
     // Init logger
     env_logger::init();
 
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    if arguments.first().map(|argument| argument.as_str()) == Some(CONVERT_STRINGS_COMMAND) {
+        std::process::exit(convert_strings_to_fluent(&arguments[1..]));
+    }
+
     // Init dotenv
-    let home_dir = dirs::home_dir().unwrap();
-    dotenv::from_filename(home_dir.join(".drink-o-matic/.env")).ok();
+    if let Some(home_dir) = dirs::home_dir() {
+        dotenv::from_filename(home_dir.join(".drink-o-matic/.env")).ok();
+    }
+
+    let mut diagnostics_report = DiagnosticsReport::default();
+
+    // Load and validate config
+    let config = match ConfigFactory::create() {
+        Ok(config) => {
+            diagnostics_report.add("config", DiagnosticStatus::Pass, format!("Loaded config for {} pumps", config.get_number_of_pumps()));
+            Some(config)
+        },
+        Err(config_errors) => {
+            let config_error_messages: Vec<String> = config_errors.iter().map(|config_error| config_error.to_string()).collect();
+            diagnostics_report.add("config", DiagnosticStatus::Fail, config_error_messages.join("; "));
+            None
+        }
+    };
+
+    // Make sure we're the only instance driving the pumps; held until the API shuts down
+    let instance_lock = run_check(&mut diagnostics_report, "instance_lock", config.as_ref(), |config| {
+        (InstanceLock::acquire(config.lock_file_path.clone()), format!("Acquired {}", config.lock_file_path.display()))
+    });
+    if config.is_some() && instance_lock.is_none() {
+        println!("{}", diagnostics_report);
+        std::process::exit(1);
+    }
 
     // Create resource service
-    let resource_service = ResourceServiceFactory::create_or_panic();
+    let (resource_service, resource_warnings) = ResourceServiceFactory::create(config.as_ref());
+    if resource_warnings.is_empty() {
+        diagnostics_report.add("strings_file", DiagnosticStatus::Pass, format!("Loaded every string for locales {}", resource_service.get_locales().join(", ")));
+    }
+    else {
+        diagnostics_report.add("strings_file", DiagnosticStatus::Warn, resource_warnings.join("; "));
+    }
     let resource_service_arc = Arc::new(resource_service);
 
-    // Create pump service
-    let mut pump_service = PumpServiceFactory::create_or_panic(resource_service_arc.clone());
-    let number_of_pumps = pump_service.get_number_of_pumps();
-    pump_service.start_daemon();
-    let pump_service_arc = Arc::new(Mutex::new(pump_service));
+    // Acquire GPIO
+    let chip = run_check(&mut diagnostics_report, "gpio_chip", config.as_ref(), |config| {
+        (PumpServiceFactory::open_chip(&resource_service_arc, config), format!("Opened chip \"{}\"", config.rpi_chip_name))
+    });
+    let pump_drivers = run_check(&mut diagnostics_report, "gpio_lines", config.as_ref().zip(chip), |(config, mut chip)| {
+        let pass_message = match config.direction_pin_numbers.iter().any(Option::is_some) {
+            true => format!("Requested lines for pins {:?} and direction lines for pins {:?}", config.ordered_pump_pin_numbers, config.direction_pin_numbers.iter().flatten().collect::<Vec<_>>()),
+            false => format!("Requested lines for pins {:?}", config.ordered_pump_pin_numbers)
+        };
+        (PumpServiceFactory::request_pump_drivers(&resource_service_arc, config, &mut chip), pass_message)
+    });
 
     let mut routes = routes![
-        pumps_options,
-        pumps_get,
-        pump_queue_options,
-        pump_queue_get,
-        pump_number_options,
-        pump_number_get,
-        pump_number_post
+        preflight_options,
+        diagnostics_get,
+        health_live_get,
+        health_ready_get,
+        me_get
     ];
-    
-    let mut rocket_builder = rocket::build();
+
+    // Serve HTTPS when a certificate is configured; plain HTTP is never used as a fallback
+    let tls_file_paths = config.as_ref().and_then(|config| config.tls_certificate_file_path.clone().zip(config.tls_key_file_path.clone()));
+    let mut figment = rocket::Config::figment();
+    if let Some((tls_certificate_file_path, tls_key_file_path)) = tls_file_paths {
+        let tls_files_result = fs::metadata(&tls_certificate_file_path).map_err(|error| format!("Couldn't read {}: {}", tls_certificate_file_path.display(), error))
+            .and(fs::metadata(&tls_key_file_path).map_err(|error| format!("Couldn't read {}: {}", tls_key_file_path.display(), error)));
+        diagnostics_report.add_result("tls_files", &tls_files_result, format!("Serving HTTPS with {}", tls_certificate_file_path.display()));
+        if tls_files_result.is_err() {
+            println!("{}", diagnostics_report);
+            std::process::exit(1);
+        }
+        figment = figment.merge(("tls.certs", tls_certificate_file_path)).merge(("tls.key", tls_key_file_path));
+    }
+    else if config.is_some() {
+        diagnostics_report.add("tls_files", DiagnosticStatus::Pass, "No certificate is configured so the API is served over plain HTTP".to_string());
+    }
+
+    let mut rocket_builder = rocket::custom(figment);
+
+    // Create API key service; keys can only be managed when the file loaded so a corrupt one is never overwritten
+    let api_key_service = run_check(&mut diagnostics_report, "api_keys_file", config.as_ref(), |config| {
+        let pass_message = match config.is_authentication_enabled {
+            true => format!("Authentication is enabled with keys from {}", config.api_keys_file_path.display()),
+            false => "Authentication is disabled so every request is treated as coming from an admin".to_string()
+        };
+        (ApiKeyServiceFactory::create(config), pass_message)
+    });
+    if config.as_ref().is_some_and(|config| !config.is_authentication_enabled) {
+        log::warn!("Authentication is disabled so anyone on the network can pour, change settings and manage API keys");
+        diagnostics_report.add("authentication", DiagnosticStatus::Warn, "Anyone on the network can pour, change settings and manage API keys; create an admin key and restart to require keys".to_string());
+    }
+    if let Some(api_key_service) = api_key_service {
+        routes.append(&mut routes![api_keys_get, api_keys_post, api_key_delete]);
+        rocket_builder = rocket_builder.manage(api_key_service);
+    }
+    else if let Some(config) = config.as_ref() {
+        // Refuse every key rather than letting requests through unauthenticated
+        rocket_builder = rocket_builder.manage(ApiKeyService::new(vec![], config.api_keys_file_path.clone(), config.is_authentication_enabled));
+    }
+    // Create pump service
+    let mut pump_service_arc = None;
+    let pump_service = run_check(&mut diagnostics_report, "pumps_file", config.as_ref().zip(pump_drivers), |(config, pump_drivers)| {
+        (PumpServiceFactory::create(resource_service_arc.clone(), config, pump_drivers), format!("Loaded pump records from {}", config.pumps_file_path.display()))
+    });
+    if let Some((config, mut pump_service)) = config.as_ref().zip(pump_service) {
+        pump_service.start_daemon();
+        let arc = Arc::new(Mutex::new(pump_service));
+        routes.append(&mut routes![
+            pumps_get,
+            pump_queue_get,
+            pump_queue_summary_get,
+            pump_queue_post,
+            pump_queue_move_post,
+            pump_queue_stop_post,
+            pump_queue_delete,
+            pump_number_get,
+            pump_number_post,
+            pump_number_prime_post,
+            pump_number_purge_post,
+            pumps_prime_post,
+            pumps_purge_post,
+            maintenance_get,
+            maintenance_task_post
+        ]);
+        rocket_builder = rocket_builder
+            .manage(arc.clone())
+            .manage(UnitsOfMeasure::new(config.units_of_measure.clone()))
+            .manage(IdempotencyService::new(Duration::from_secs(config.idempotency_window_seconds)));
+        pump_service_arc = Some(arc);
+    }
+
     // Optionally adds my crude back-end for front-end logic
-    rocket_builder = optionally_attach_settings_endpoint(rocket_builder, &mut routes, resource_service_arc.clone(), number_of_pumps);
-    let _rocket = rocket_builder.attach(CORS)
+    rocket_builder = optionally_attach_bff_endpoints(rocket_builder, &mut routes, &mut diagnostics_report, config.as_ref(), pump_service_arc.clone());
+
+    // Create schedule service once orders can be placed, so scheduled ones can be too
+    let schedule_service = run_check(&mut diagnostics_report, "schedules_file", config.as_ref().zip(pump_service_arc.clone()), |(config, pump_service)| {
+        let schedule_service = ScheduleServiceFactory::create(
+            config,
+            pump_service,
+            #[cfg(feature = "bff")]
+            rocket_builder.state::<Arc<OrderService>>().cloned()
+        );
+        (schedule_service, format!("Loaded schedules from {}", config.schedules_file_path.display()))
+    }).map(Arc::new);
+    if let Some(schedule_service) = &schedule_service {
+        ScheduleService::start_daemon(schedule_service);
+        routes.append(&mut routes![schedules_get, schedules_post, schedule_put, schedule_delete]);
+        rocket_builder = rocket_builder.manage(schedule_service.clone());
+    }
+
+    // Create cleaning service
+    let cleaning_service = run_check(&mut diagnostics_report, "cleaning_programs_file", config.as_ref().zip(pump_service_arc.clone()), |(config, pump_service)| {
+        (CleaningServiceFactory::create(config, pump_service), format!("Loaded cleaning programs from {}", config.cleaning_programs_file_path.display()))
+    }).map(Arc::new);
+    if let Some(cleaning_service) = &cleaning_service {
+        routes.append(&mut routes![cleaning_programs_get, cleaning_run_get, cleaning_run_post, cleaning_run_confirm_post, cleaning_run_delete]);
+        rocket_builder = rocket_builder.manage(cleaning_service.clone());
+    }
+
+    // Always print the report so a misconfigured machine explains itself
+    println!("{}", diagnostics_report);
+    if diagnostics_report.has_failures() {
+        log::error!("Startup checks failed; endpoints depending on them will not be served until the problems are fixed and the API is restarted");
+    }
+
+    let health_service = HealthService::new(pump_service_arc.clone(), diagnostics_report.get_status("gpio_lines"), diagnostics_report.get_status("settings_file"));
+    let cors_fairing = match config.as_ref() {
+        Some(config) => CorsFairing::new(config.cors_allowed_origins.clone(), config.cors_allowed_methods.clone(), config.cors_allowed_headers.clone()),
+        // Only diagnostics are served without a config
+        None => CorsFairing::new(vec!["*".to_string()], vec!["GET".to_string()], vec!["Content-Type".to_string()])
+    };
+    let _rocket = rocket_builder.attach(cors_fairing)
         .mount("/", routes)
-        .manage(pump_service_arc.clone())
-        .manage(resource_service_arc.clone())
+        .register("/", catchers![bad_request_catcher, unauthorized_catcher, forbidden_catcher, not_found_catcher, unprocessable_entity_catcher, internal_server_error_catcher])
+        .manage(health_service)
+        .manage(resource_service_arc)
+        .manage(diagnostics_report)
         .ignite().await?
         .launch().await?;
 
-    pump_service_arc.lock().unwrap().kill_daemon();
+    if let Some(schedule_service) = schedule_service {
+        schedule_service.kill_daemon();
+    }
+    if let Some(cleaning_service) = cleaning_service {
+        cleaning_service.kill_cleaning_run();
+    }
+    if let Some(pump_service_arc) = pump_service_arc {
+        pump_service_arc.lock().unwrap().kill_daemon();
+    }
+    drop(instance_lock);
     Ok(())
 }